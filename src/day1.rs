@@ -16,7 +16,7 @@ pub fn part1(input: &str) -> usize {
             last = val;
         });
 
-    return count;
+    count
 }
 
 #[aoc(day1, part2)]
@@ -39,7 +39,7 @@ pub fn part2(input: &str) -> usize {
         last = sum;
     });
 
-    return count;
+    count
 }
 
 #[cfg(test)]
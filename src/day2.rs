@@ -14,7 +14,7 @@ pub fn input_generator(input: &str) -> Vec<Command> {
             let direction: String = parts.next().unwrap().to_string();
             let magnitude: usize = parts.next().unwrap().parse::<usize>().unwrap();
 
-            return Command {
+            Command {
                 direction,
                 magnitude,
             }
@@ -43,7 +43,7 @@ pub fn part1(input: &[Command]) -> usize {
             }
         });
 
-    return depth * position;
+    depth * position
 }
 
 #[aoc(day2, part2)]
@@ -70,7 +70,7 @@ pub fn part2(input: &[Command]) -> usize {
             }
         });
 
-    return depth * position;
+    depth * position
 }
 
 
@@ -1,100 +1,468 @@
+use std::error::Error;
+use std::fmt;
 use std::iter::{Iterator, FromIterator};
 
-pub fn filter_by_common(input: &Vec<Vec<char>>, position: usize, most: bool) -> Vec<Vec<char>> {
-    let common_value = match most {
-        true => most_common_value(input, position),
-        false => least_common_value(input, position)
+// Two rates of at most 64 bits always multiply within a u128.
+const MAX_WIDTH: usize = 64;
+
+#[derive(Debug, PartialEq)]
+pub enum ReportError {
+    Empty,
+    TooWide { width: usize },
+    Tie { position: usize },
+    NoRowRemaining { position: usize },
+    RaggedRow { row: usize, expected: usize, found: usize },
+    InvalidCharacter { row: usize, column: usize, found: char }
+}
+
+impl fmt::Display for ReportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReportError::Empty => write!(f, "diagnostic report is empty"),
+            ReportError::TooWide { width } => {
+                write!(f, "diagnostic report is {} bits wide, at most {} are supported", width, MAX_WIDTH)
+            },
+            ReportError::Tie { position } => write!(f, "ones and zeroes tie at bit {}", position),
+            ReportError::RaggedRow { row, expected, found } => {
+                write!(f, "row {} is {} bits wide, expected {}", row, found, expected)
+            },
+            ReportError::NoRowRemaining { position } => {
+                write!(f, "no row remains after filtering on bit {}", position)
+            },
+            ReportError::InvalidCharacter { row, column, found } => {
+                write!(f, "row {} has invalid character {:?} at column {}", row, found, column)
+            }
+        }
+    }
+}
+
+impl Error for ReportError {}
+
+pub fn parse_report(input: &str) -> Result<Vec<Vec<char>>, ReportError> {
+    let values: Vec<Vec<char>> = input
+        .lines()
+        .map(|val| { val.trim().chars().collect() })
+        .collect::<Vec<Vec<char>>>();
+
+    validate_report(&values)?;
+
+    Ok(values)
+}
+
+pub fn validate_report(values: &[Vec<char>]) -> Result<usize, ReportError> {
+    let width = match values.first() {
+        Some(row) if !row.is_empty() => row.len(),
+        _ => return Err(ReportError::Empty)
     };
-    let filtered_rows = filter_by_value(input, position, common_value);
 
-    match filtered_rows.len() {
-        0 => filtered_rows,
-        1 => filtered_rows,
-        _ => filter_by_common(&filtered_rows, position + 1, most)
+    if width > MAX_WIDTH {
+        return Err(ReportError::TooWide { width });
+    }
+
+    for (i, row) in values.iter().enumerate() {
+        if row.len() != width {
+            return Err(ReportError::RaggedRow { row: i + 1, expected: width, found: row.len() });
+        }
+
+        if let Some((j, &c)) = row.iter().enumerate().find(|(_, &c)| c != '0' && c != '1') {
+            return Err(ReportError::InvalidCharacter { row: i + 1, column: j + 1, found: c });
+        }
     }
+
+    Ok(width)
 }
 
-pub fn filter_by_value(input: &Vec<Vec<char>>, position: usize, value: char) -> Vec<Vec<char>> {
-   input
-       .into_iter()
-       .filter(|val| {
-           val[position] == value
-       })
-       .cloned()
-       .collect::<Vec<Vec<char>>>()
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Commonality {
+    Most,
+    Least
 }
 
-pub fn most_common_value(input: &Vec<Vec<char>>, position: usize) -> char {
-    let (ones, zeroes): (Vec<char>, Vec<char>) = input
-        .into_iter()
-        .map(|val| val[position] )
-        .partition(|&n| n == '1' );
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TieBreak {
+    PreferOne,
+    PreferZero,
+    Error
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BitCriteria {
+    pub commonality: Commonality,
+    pub tie_break: TieBreak
+}
 
-    match ones.len() >= zeroes.len() {
-        true => '1',
-        false => '0'
+impl BitCriteria {
+    pub fn most_common() -> BitCriteria {
+        BitCriteria { commonality: Commonality::Most, tie_break: TieBreak::PreferOne }
     }
+
+    pub fn least_common() -> BitCriteria {
+        BitCriteria { commonality: Commonality::Least, tie_break: TieBreak::PreferZero }
+    }
+
+    pub fn with_tie_break(self, tie_break: TieBreak) -> BitCriteria {
+        BitCriteria { tie_break, ..self }
+    }
+
+    pub fn select(&self, ones: usize, zeroes: usize, position: usize) -> Result<char, ReportError> {
+        if ones == zeroes {
+            return match self.tie_break {
+                TieBreak::PreferOne => Ok('1'),
+                TieBreak::PreferZero => Ok('0'),
+                TieBreak::Error => Err(ReportError::Tie { position })
+            };
+        }
+
+        match (self.commonality, ones > zeroes) {
+            (Commonality::Most, true) | (Commonality::Least, false) => Ok('1'),
+            (Commonality::Most, false) | (Commonality::Least, true) => Ok('0')
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct BitSelection {
+    pub bits: Vec<char>,
+    pub ties: Vec<usize>
 }
 
-pub fn least_common_value(input: &Vec<Vec<char>>, position: usize) -> char {
+#[derive(Clone, Default)]
+struct TrieNode {
+    children: [Option<usize>; 2],
+    count: usize
+}
+
+pub struct BitTrie {
+    nodes: Vec<TrieNode>,
+    width: usize
+}
+
+impl BitTrie {
+    pub fn new(width: usize) -> BitTrie {
+        BitTrie { nodes: vec![TrieNode::default()], width }
+    }
+
+    pub fn from_rows(rows: &[Vec<char>]) -> Result<BitTrie, ReportError> {
+        let mut trie = BitTrie::new(validate_report(rows)?);
+
+        for row in rows {
+            trie.insert(row)?;
+        }
+
+        Ok(trie)
+    }
+
+    pub fn insert(&mut self, row: &[char]) -> Result<(), ReportError> {
+        if row.len() != self.width {
+            return Err(ReportError::RaggedRow { row: self.len() + 1, expected: self.width, found: row.len() });
+        }
+
+        let bits = row
+            .iter()
+            .enumerate()
+            .map(|(j, &c)| BitTrie::bit_index(c).ok_or(ReportError::InvalidCharacter { row: self.len() + 1, column: j + 1, found: c }))
+            .collect::<Result<Vec<usize>, ReportError>>()?;
+
+        let mut node = 0;
+        self.nodes[node].count += 1;
+
+        for bit in bits {
+
+            node = match self.nodes[node].children[bit] {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(TrieNode::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children[bit] = Some(child);
+                    child
+                }
+            };
+
+            self.nodes[node].count += 1;
+        }
+
+        Ok(())
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes[0].count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn count_prefix(&self, prefix: &[char]) -> usize {
+        self.find_node(prefix).map_or(0, |node| self.nodes[node].count)
+    }
+
+    pub fn rows_with_prefix(&self, prefix: &[char]) -> Vec<Vec<char>> {
+        let mut rows: Vec<Vec<char>> = vec![];
+
+        if let Some(node) = self.find_node(prefix) {
+            self.collect_rows(node, &mut prefix.to_vec(), &mut rows);
+        }
+
+        rows
+    }
+
+    pub fn find_rating(&self, criteria: BitCriteria) -> Result<BitSelection, ReportError> {
+        let mut node = 0;
+        let mut bits: Vec<char> = Vec::with_capacity(self.width);
+        let mut ties: Vec<usize> = vec![];
+
+        while bits.len() < self.width {
+            let position = bits.len();
+            let ones = self.child_count(node, 1);
+            let zeroes = self.child_count(node, 0);
+
+            let value = match self.nodes[node].count {
+                0 => return Err(ReportError::NoRowRemaining { position }),
+                1 if ones == 1 => '1',
+                1 => '0',
+                _ => {
+                    if ones == zeroes {
+                        ties.push(position);
+                    }
+                    criteria.select(ones, zeroes, position)?
+                }
+            };
+
+            node = self.nodes[node].children[BitTrie::bit_index(value).unwrap_or(0)]
+                .ok_or(ReportError::NoRowRemaining { position })?;
+            bits.push(value);
+        }
+
+        Ok(BitSelection { bits, ties })
+    }
+
+    pub fn trace(&self, criteria: BitCriteria) -> Result<Vec<FilterStep>, ReportError> {
+        let mut node = 0;
+        let mut prefix: Vec<char> = Vec::with_capacity(self.width);
+        let mut steps: Vec<FilterStep> = vec![];
+
+        while prefix.len() < self.width && self.nodes[node].count > 1 {
+            let position = prefix.len();
+            let ones = self.child_count(node, 1);
+            let zeroes = self.child_count(node, 0);
+            let kept = criteria.select(ones, zeroes, position)?;
+
+            let mut dropped = prefix.clone();
+            dropped.push(match kept {
+                '1' => '0',
+                _ => '1'
+            });
+
+            let child = self.nodes[node].children[BitTrie::bit_index(kept).unwrap_or(0)];
+
+            steps.push(FilterStep {
+                position,
+                ones,
+                zeroes,
+                kept,
+                remaining: child.map_or(0, |child| self.nodes[child].count),
+                eliminated: self.rows_with_prefix(&dropped)
+            });
+
+            match child {
+                Some(child) => node = child,
+                None => break
+            }
+            prefix.push(kept);
+        }
+
+        Ok(steps)
+    }
+
+    fn find_node(&self, prefix: &[char]) -> Option<usize> {
+        prefix
+            .iter()
+            .try_fold(0, |node, &c| self.nodes[node].children[BitTrie::bit_index(c)?])
+    }
+
+    fn collect_rows(&self, node: usize, prefix: &mut Vec<char>, rows: &mut Vec<Vec<char>>) {
+        if prefix.len() == self.width {
+            for _ in 0..self.nodes[node].count {
+                rows.push(prefix.clone());
+            }
+            return;
+        }
+
+        for (bit, value) in ['0', '1'].iter().enumerate() {
+            if let Some(child) = self.nodes[node].children[bit] {
+                prefix.push(*value);
+                self.collect_rows(child, prefix, rows);
+                prefix.pop();
+            }
+        }
+    }
+
+    fn child_count(&self, node: usize, bit: usize) -> usize {
+        self.nodes[node].children[bit].map_or(0, |child| self.nodes[child].count)
+    }
+
+    fn bit_index(c: char) -> Option<usize> {
+        match c {
+            '0' => Some(0),
+            '1' => Some(1),
+            _ => None
+        }
+    }
+}
+
+fn count_column(input: &[Vec<char>], position: usize) -> (usize, usize) {
     let (ones, zeroes): (Vec<char>, Vec<char>) = input
-        .into_iter()
+        .iter()
         .map(|val| val[position] )
         .partition(|&n| n == '1' );
 
-    match ones.len() >= zeroes.len() {
-        true => '0',
-        false => '1'
-    }
+    (ones.len(), zeroes.len())
 }
 
-pub fn compute_significant_bitmap(input: &Vec<Vec<char>>, most: bool) -> u32 {
-    let mut bit_string: Vec<char> = Vec::new();
-    let length = input[0].len();
+pub fn select_bits(input: &[Vec<char>], criteria: BitCriteria) -> Result<BitSelection, ReportError> {
+    let mut bits: Vec<char> = Vec::new();
+    let mut ties: Vec<usize> = vec![];
+    let length = validate_report(input)?;
 
     for i in 0..length {
-        let new_value = match most {
-            true => most_common_value(input, i),
-            false => least_common_value(input, i)
-        };
+        let (ones, zeroes) = count_column(input, i);
+
+        if ones == zeroes {
+            ties.push(i);
+        }
 
-        bit_string.push(new_value)
+        bits.push(criteria.select(ones, zeroes, i)?)
     }
 
-    u32::from_str_radix(&String::from_iter(bit_string), 2).unwrap()
+    Ok(BitSelection { bits, ties })
 }
 
-pub fn chars_to_int(input: &Vec<char>) -> u32 {
-    u32::from_str_radix(&String::from_iter(input), 2).unwrap()
+pub fn compute_significant_bitmap(input: &[Vec<char>], criteria: BitCriteria) -> Result<u128, ReportError> {
+    Ok(chars_to_int(&select_bits(input, criteria)?.bits))
 }
 
+#[derive(Debug, PartialEq)]
+pub struct ColumnStats {
+    pub position: usize,
+    pub ones: usize,
+    pub zeroes: usize
+}
 
-#[aoc(day3, part1)]
-pub fn part1(input: &str)  -> u32 {
-    let values: Vec<Vec<char>> = input
-        .lines()
-        .map(|val| { val.chars().collect() })
-        .collect::<Vec<Vec<char>>>();
+impl ColumnStats {
+    pub fn ones_ratio(&self) -> f64 {
+        self.ones as f64 / (self.ones + self.zeroes) as f64
+    }
+
+    pub fn zeroes_ratio(&self) -> f64 {
+        self.zeroes as f64 / (self.ones + self.zeroes) as f64
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct FilterStep {
+    pub position: usize,
+    pub ones: usize,
+    pub zeroes: usize,
+    pub kept: char,
+    pub remaining: usize,
+    pub eliminated: Vec<Vec<char>>
+}
+
+pub struct DiagnosticReport {
+    pub columns: Vec<ColumnStats>,
+    pub oxygen: Vec<FilterStep>,
+    pub co2: Vec<FilterStep>
+}
+
+impl DiagnosticReport {
+    pub fn new(input: &[Vec<char>]) -> Result<DiagnosticReport, ReportError> {
+        let length = validate_report(input)?;
+        let columns = (0..length)
+            .map(|position| {
+                let (ones, zeroes) = count_column(input, position);
+                ColumnStats { position, ones, zeroes }
+            })
+            .collect::<Vec<ColumnStats>>();
+
+        let trie = BitTrie::from_rows(input)?;
 
-    let gamma = compute_significant_bitmap(&values, true);
-    let epsilon = compute_significant_bitmap(&values, false);
+        Ok(DiagnosticReport {
+            columns,
+            oxygen: trie.trace(BitCriteria::most_common())?,
+            co2: trie.trace(BitCriteria::least_common())?
+        })
+    }
+
+    fn fmt_trace(f: &mut fmt::Formatter, name: &str, steps: &[FilterStep]) -> fmt::Result {
+        writeln!(f, "{}", name)?;
+        writeln!(f, "{:>4} {:>6} {:>6} {:>5} {:>10}  eliminated", "bit", "ones", "zeroes", "kept", "remaining")?;
+
+        for step in steps {
+            let eliminated = step.eliminated
+                .iter()
+                .map(String::from_iter)
+                .collect::<Vec<String>>()
+                .join(", ");
+
+            writeln!(f, "{:>4} {:>6} {:>6} {:>5} {:>10}  {}", step.position, step.ones, step.zeroes, step.kept, step.remaining, eliminated)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for DiagnosticReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>4} {:>6} {:>6} {:>7} {:>7}", "bit", "ones", "zeroes", "ones%", "zeroes%")?;
+
+        for column in &self.columns {
+            writeln!(
+                f,
+                "{:>4} {:>6} {:>6} {:>6.1}% {:>6.1}%",
+                column.position,
+                column.ones,
+                column.zeroes,
+                column.ones_ratio() * 100.0,
+                column.zeroes_ratio() * 100.0
+            )?;
+        }
 
-    let product = gamma * epsilon;
+        writeln!(f)?;
+        DiagnosticReport::fmt_trace(f, "oxygen", &self.oxygen)?;
+        writeln!(f)?;
+        DiagnosticReport::fmt_trace(f, "co2", &self.co2)
+    }
+}
 
-    product
+pub fn chars_to_int(input: &[char]) -> u128 {
+    input.iter().fold(0, |value, &c| value << 1 | (c == '1') as u128)
+}
+
+
+#[aoc(day3, part1)]
+pub fn part1(input: &str) -> Result<u128, ReportError> {
+    let values = parse_report(input)?;
+
+    let gamma = compute_significant_bitmap(&values, BitCriteria::most_common())?;
+    let epsilon = compute_significant_bitmap(&values, BitCriteria::least_common())?;
+
+    Ok(gamma * epsilon)
 }
 
 #[aoc(day3, part2)]
-pub fn part2(input: &str) ->  u32 {
-    let values: Vec<Vec<char>> = input
-        .lines()
-        .map(|val| { val.chars().collect() })
-        .collect::<Vec<Vec<char>>>();
+pub fn part2(input: &str) -> Result<u128, ReportError> {
+    let values = parse_report(input)?;
+
+    let trie = BitTrie::from_rows(&values)?;
 
-    let oxygen_rating = chars_to_int(&filter_by_common(&values, 0, true)[0]);
-    let co2_rating = chars_to_int(&filter_by_common(&values, 0, false)[0]);
+    let oxygen_rating = chars_to_int(&trie.find_rating(BitCriteria::most_common())?.bits);
+    let co2_rating = chars_to_int(&trie.find_rating(BitCriteria::least_common())?.bits);
 
-    oxygen_rating * co2_rating
+    Ok(oxygen_rating * co2_rating)
 }
 
 #[cfg(test)]
@@ -105,14 +473,155 @@ mod tests {
     fn example1() {
         let input = "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010\n";
 
-        assert_eq!(part1(input), 198)
+        assert_eq!(part1(input), Ok(198))
     }
 
     #[test]
     fn example2() {
         let input = "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010\n";
 
-        assert_eq!(part2(input), 230)
+        assert_eq!(part2(input), Ok(230))
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn trie_prefix_queries() {
+        let input = "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010\n";
+        let trie = BitTrie::from_rows(&parse_report(input).unwrap()).unwrap();
+
+        assert_eq!(trie.len(), 12);
+        assert_eq!(trie.count_prefix(&['1']), 7);
+        assert_eq!(trie.count_prefix(&['1', '0', '1']), 3);
+        assert_eq!(trie.count_prefix(&['0', '0', '0', '0']), 0);
+        assert_eq!(trie.rows_with_prefix(&['1', '0', '1', '1']), vec![vec!['1', '0', '1', '1', '0'], vec!['1', '0', '1', '1', '1']])
+    }
+
+    #[test]
+    fn trie_ratings() {
+        let input = "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010\n";
+        let trie = BitTrie::from_rows(&parse_report(input).unwrap()).unwrap();
+
+        let oxygen = trie.find_rating(BitCriteria::most_common()).unwrap();
+        let co2 = trie.find_rating(BitCriteria::least_common()).unwrap();
+
+        assert_eq!(oxygen.bits, vec!['1', '0', '1', '1', '1']);
+        assert_eq!(oxygen.ties, vec![4]);
+        assert_eq!(co2.bits, vec!['0', '1', '0', '1', '0']);
+        assert_eq!(co2.ties, vec![2])
+    }
+
+    #[test]
+    fn trie_duplicate_rows() {
+        let trie = BitTrie::from_rows(&parse_report("101\n101\n").unwrap()).unwrap();
+
+        assert_eq!(trie.find_rating(BitCriteria::most_common()).unwrap().bits, vec!['1', '0', '1']);
+        assert_eq!(trie.rows_with_prefix(&[]).len(), 2)
+    }
+
+    #[test]
+    fn tie_breaking() {
+        let values = parse_report("10\n01\n11\n").unwrap();
+
+        let preferred = select_bits(&values[..2], BitCriteria::least_common().with_tie_break(TieBreak::PreferOne)).unwrap();
+        assert_eq!(preferred, BitSelection { bits: vec!['1', '1'], ties: vec![0, 1] });
+
+        let trie = BitTrie::from_rows(&values).unwrap();
+        let oxygen = trie.find_rating(BitCriteria::most_common().with_tie_break(TieBreak::PreferZero)).unwrap();
+        assert_eq!(oxygen, BitSelection { bits: vec!['1', '0'], ties: vec![1] });
+
+        let strict = BitCriteria::most_common().with_tie_break(TieBreak::Error);
+        assert_eq!(trie.find_rating(strict), Err(ReportError::Tie { position: 1 }))
+    }
+
+    #[test]
+    fn diagnostic_report() {
+        let input = "00100\n11110\n10110\n10111\n10101\n01111\n00111\n11100\n10000\n11001\n00010\n01010\n";
+        let report = DiagnosticReport::new(&parse_report(input).unwrap()).unwrap();
+
+        assert_eq!(report.columns[0], ColumnStats { position: 0, ones: 7, zeroes: 5 });
+        assert_eq!(report.oxygen.len(), 5);
+        assert_eq!(report.oxygen[0].remaining, 7);
+        assert_eq!(report.oxygen[4].eliminated, vec![vec!['1', '0', '1', '1', '0']]);
+        assert_eq!(report.co2.iter().map(|step| step.remaining).collect::<Vec<usize>>(), vec![5, 2, 1]);
+
+        let table = report.to_string();
+        assert!(table.contains("   0      7      5   58.3%   41.7%"));
+        assert!(table.contains("   4      1      1     1          1  10110"))
+    }
+
+    #[test]
+    fn wide_report() {
+        let input = "0000000000000000000000000000000000000001\n1000000000000000000000000000000000000001\n1000000000000000000000000000000000000000\n";
+
+        assert_eq!(compute_significant_bitmap(&parse_report(input).unwrap(), BitCriteria::most_common()), Ok(0x80_0000_0001));
+        assert_eq!(part1(input), Ok(0x80_0000_0001 * 0x7f_ffff_fffe))
+    }
+
+    #[test]
+    fn ragged_report() {
+        let input = "00100\n11110\n1011\n";
+
+        assert_eq!(part1(input), Err(ReportError::RaggedRow { row: 3, expected: 5, found: 4 }))
+    }
+
+    #[test]
+    fn invalid_character() {
+        let input = "00100\n11110\n10x10\n";
+
+        assert_eq!(part2(input), Err(ReportError::InvalidCharacter { row: 3, column: 3, found: 'x' }))
+    }
+
+    #[test]
+    fn no_row_remaining() {
+        let input = "10\n11\n";
+
+        assert_eq!(part2(input), Err(ReportError::NoRowRemaining { position: 0 }))
+    }
+
+    #[test]
+    fn too_wide_report() {
+        let input = "0".repeat(65);
+
+        assert_eq!(part1(&input), Err(ReportError::TooWide { width: 65 }))
+    }
+
+    #[test]
+    fn product_width_limit() {
+        let report = |width: usize| {
+            let one = format!("10{}", "1".repeat(width - 2));
+            format!("{}\n{}\n01{}\n", one, one, "0".repeat(width - 2))
+        };
+
+        assert_eq!(part1(&report(64)), Ok(((1u128 << 63) + (1 << 62) - 1) * (1 << 62)));
+        assert_eq!(part2(&report(64)), Ok(((1u128 << 63) + (1 << 62) - 1) * (1 << 62)));
+        assert_eq!(part1(&report(65)), Err(ReportError::TooWide { width: 65 }));
+        assert_eq!(part2(&report(65)), Err(ReportError::TooWide { width: 65 }))
+    }
+
+    #[test]
+    fn unvalidated_rows() {
+        let ragged = vec![vec!['1', '0'], vec!['1']];
+
+        assert_eq!(DiagnosticReport::new(&ragged).err(), Some(ReportError::RaggedRow { row: 2, expected: 2, found: 1 }));
+        assert_eq!(DiagnosticReport::new(&[]).err(), Some(ReportError::Empty));
+        assert_eq!(compute_significant_bitmap(&[], BitCriteria::most_common()), Err(ReportError::Empty));
+        assert_eq!(compute_significant_bitmap(&ragged, BitCriteria::most_common()), Err(ReportError::RaggedRow { row: 2, expected: 2, found: 1 }));
+        assert_eq!(
+            select_bits(&[vec!['1', '2']], BitCriteria::most_common()),
+            Err(ReportError::InvalidCharacter { row: 1, column: 2, found: '2' })
+        );
+        assert_eq!(chars_to_int(&[]), 0)
+    }
+
+    #[test]
+    fn trie_rejects_invalid_rows() {
+        let mut trie = BitTrie::new(3);
+
+        assert_eq!(trie.insert(&['1', '0', '1']), Ok(()));
+        assert_eq!(trie.insert(&['1', 'x', '1']), Err(ReportError::InvalidCharacter { row: 2, column: 2, found: 'x' }));
+        assert_eq!(trie.insert(&['1', '0']), Err(ReportError::RaggedRow { row: 2, expected: 3, found: 2 }));
+        assert_eq!(trie.len(), 1);
+        assert_eq!(trie.count_prefix(&['1', 'x']), 0);
+        assert_eq!(trie.count_prefix(&['1', '0']), 1);
+        assert_eq!(BitTrie::from_rows(&[vec!['1'], vec!['2']]).err(), Some(ReportError::InvalidCharacter { row: 2, column: 1, found: '2' }))
+    }
+}
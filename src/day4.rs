@@ -1,152 +1,1018 @@
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::env;
+use std::io::{self, BufRead, Write};
 use std::iter::FromIterator;
+use std::str::FromStr;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+const WIN_RULES_VAR: &str = "DAY4_WIN_RULES";
+const GENERATOR_ATTEMPTS: usize = 1000;
 
 type ProblemInput = (Vec<u64>, Vec<Board>);
 
+#[derive(Debug, PartialEq)]
+pub enum BingoError {
+    MissingBoards,
+    EmptyBoard { board: usize },
+    SquareCount { board: usize, expected: usize, found: usize },
+    InvalidNumber { value: String },
+    RaggedBoard { board: usize, row: usize, expected: usize, found: usize },
+    MismatchedBoard { board: usize, expected: (usize, usize), found: (usize, usize) },
+    InvalidPuzzleConfig { reason: String },
+    GenerationFailed { attempts: usize },
+    UnknownWinRule { value: String },
+    UnsupportedWinRule { rule: WinRule, width: usize, height: usize },
+    InvalidMark { turn: usize, board: u64, cell: usize }
+}
+
+impl fmt::Display for BingoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BingoError::MissingBoards => write!(f, "no boards found after the draw order"),
+            BingoError::EmptyBoard { board } => write!(f, "board {} has no squares", board),
+            BingoError::SquareCount { board, expected, found } => {
+                write!(f, "board {} has {} squares, expected {}", board, found, expected)
+            },
+            BingoError::InvalidNumber { value } => write!(f, "{:?} is not a valid bingo number", value),
+            BingoError::RaggedBoard { board, row, expected, found } => {
+                write!(f, "board {} row {} has {} squares, expected {}", board, row, found, expected)
+            },
+            BingoError::MismatchedBoard { board, expected, found } => {
+                write!(f, "board {} is {}x{}, expected {}x{}", board, found.0, found.1, expected.0, expected.1)
+            },
+            BingoError::InvalidPuzzleConfig { reason } => write!(f, "invalid puzzle config: {}", reason),
+            BingoError::GenerationFailed { attempts } => {
+                write!(f, "no puzzle matched the target outcome after {} attempts", attempts)
+            },
+            BingoError::UnknownWinRule { value } => {
+                write!(f, "{:?} is not a win rule, expected lines, diagonals, corners or blackout", value)
+            },
+            BingoError::UnsupportedWinRule { rule, width, height } => {
+                write!(f, "win rule {:?} is not supported on {}x{} boards", rule, width, height)
+            },
+            BingoError::InvalidMark { turn, board, cell } => {
+                write!(f, "turn {} marks cell {} on board {}, which does not exist", turn, cell, board + 1)
+            }
+        }
+    }
+}
+
+impl Error for BingoError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum WinRule {
+    Lines,
+    Diagonals,
+    FourCorners,
+    Blackout
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Cells(Vec<u64>);
+
+impl Cells {
+    fn insert(&mut self, cell: usize) {
+        let word = cell / 64;
+
+        if word >= self.0.len() {
+            self.0.resize(word + 1, 0);
+        }
+
+        self.0[word] |= 1 << (cell % 64);
+    }
+
+    fn contains(&self, cell: usize) -> bool {
+        self.0.get(cell / 64).is_some_and(|word| word & 1 << (cell % 64) != 0)
+    }
+
+    fn contains_all(&self, other: &Cells) -> bool {
+        other.0
+            .iter()
+            .enumerate()
+            .all(|(i, &word)| self.0.get(i).cloned().unwrap_or(0) & word == word)
+    }
+
+    fn union_with(&mut self, other: &Cells) {
+        if other.0.len() > self.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+
+        self.0.iter_mut().zip(other.0.iter()).for_each(|(word, &other)| *word |= other);
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+impl FromIterator<usize> for Cells {
+    fn from_iter<I: IntoIterator<Item = usize>>(cells: I) -> Cells {
+        let mut set = Cells::default();
+        cells.into_iter().for_each(|cell| set.insert(cell));
+
+        set
+    }
+}
+
+impl WinRule {
+    fn groups(&self, width: usize, height: usize) -> Result<Vec<Cells>, BingoError> {
+        let cell = |i: usize, j: usize| i * width + j;
+        let mut groups: Vec<Cells> = vec![];
+
+        match self {
+            WinRule::Lines => {
+                for i in 0..height {
+                    groups.push((0..width).map(|j| cell(i, j)).collect());
+                }
+
+                for j in 0..width {
+                    groups.push((0..height).map(|i| cell(i, j)).collect());
+                }
+            },
+            WinRule::Diagonals => {
+                if width != height {
+                    return Err(BingoError::UnsupportedWinRule { rule: *self, width, height });
+                }
+
+                groups.push((0..width).map(|i| cell(i, i)).collect());
+                groups.push((0..width).map(|i| cell(i, width - 1 - i)).collect());
+            },
+            WinRule::FourCorners => {
+                groups.push([cell(0, 0), cell(0, width - 1), cell(height - 1, 0), cell(height - 1, width - 1)].iter().cloned().collect());
+            },
+            WinRule::Blackout => {
+                groups.push((0..width * height).collect());
+            }
+        }
+
+        Ok(groups)
+    }
+}
+
+impl FromStr for WinRule {
+    type Err = BingoError;
+
+    fn from_str(value: &str) -> Result<WinRule, BingoError> {
+        match value.trim().to_lowercase().as_str() {
+            "lines" => Ok(WinRule::Lines),
+            "diagonals" => Ok(WinRule::Diagonals),
+            "corners" => Ok(WinRule::FourCorners),
+            "blackout" => Ok(WinRule::Blackout),
+            _ => Err(BingoError::UnknownWinRule { value: value.trim().to_string() })
+        }
+    }
+}
+
+pub fn parse_win_rules(value: &str) -> Result<Vec<WinRule>, BingoError> {
+    value
+        .split(',')
+        .map(WinRule::from_str)
+        .collect::<Result<Vec<WinRule>, BingoError>>()
+}
+
 #[derive(Clone)]
 pub struct Board {
     id: u64,
+    width: usize,
+    height: usize,
     squares: Vec<u64>,
-    groups: Vec<HashSet<u64>>
+    positions: HashMap<u64, Vec<usize>>,
+    rules: Vec<WinRule>,
+    groups: Vec<Cells>,
+    marked: Cells
 }
 
 impl Board {
-    fn from_str(id: u64, input: &str) -> Board {
-        let input_str = input
+    fn from_str(id: u64, input: &str) -> Result<Board, BingoError> {
+        let board = id as usize + 1;
+        let rows = input
             .trim()
-            .replace("\n", " ");
-
-        let squares = input_str
-            .split_whitespace()
-            .map(|n| {
-                n.trim().parse::<u64>().unwrap()
+            .lines()
+            .map(|line| {
+                line
+                    .split_whitespace()
+                    .map(parse_number)
+                    .collect::<Result<Vec<u64>, BingoError>>()
             })
-            .collect::<Vec<u64>>();
+            .collect::<Result<Vec<Vec<u64>>, BingoError>>()?;
 
-        let parsed_groups =  Board::parse_groups(&squares);
+        let width = match rows.first() {
+            Some(row) if !row.is_empty() => row.len(),
+            _ => return Err(BingoError::EmptyBoard { board })
+        };
 
-        Board { id, squares, groups: parsed_groups }
+        for (i, row) in rows.iter().enumerate() {
+            if row.len() != width {
+                return Err(BingoError::RaggedBoard { board, row: i + 1, expected: width, found: row.len() });
+            }
+        }
+
+        Board::from_squares(id, width, rows.len(), rows.concat())
     }
 
-    fn parse_groups(squares: &Vec<u64>) -> Vec<HashSet<u64>> {
-        let mut groups: Vec<HashSet<u64>> = vec![];
+    pub fn from_squares(id: u64, width: usize, height: usize, squares: Vec<u64>) -> Result<Board, BingoError> {
+        let board = id as usize + 1;
 
-        for i in 0..5 {
-            let mut column: HashSet<u64>= HashSet::new();
-            let mut row: HashSet<u64> = HashSet::new();
+        if squares.is_empty() {
+            return Err(BingoError::EmptyBoard { board });
+        }
 
-            for j in 0..5 {
-                let column_index: usize = (i * 5) + j;
-                let row_index: usize = i + (j * 5);
-                column.insert(squares[column_index]);
-                row.insert(squares[row_index]);
-            }
+        if squares.len() != width * height {
+            return Err(BingoError::SquareCount { board, expected: width * height, found: squares.len() });
+        }
 
-            groups.push(column);
-            groups.push(row);
+        let mut positions: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (i, &n) in squares.iter().enumerate() {
+            positions.entry(n).or_default().push(i);
         }
 
-        groups
+        let rules = vec![WinRule::Lines];
+        let parsed_groups = Board::parse_groups(width, height, &rules)?;
+
+        Ok(Board { id, width, height, squares, positions, rules, groups: parsed_groups, marked: Cells::default() })
+    }
+
+    pub fn with_win_rules(mut self, rules: &[WinRule]) -> Result<Board, BingoError> {
+        self.groups = Board::parse_groups(self.width, self.height, rules)?;
+        self.rules = rules.to_vec();
+
+        Ok(self)
+    }
+
+    fn parse_groups(width: usize, height: usize, rules: &[WinRule]) -> Result<Vec<Cells>, BingoError> {
+        let mut groups: Vec<Cells> = vec![];
+
+        for rule in rules {
+            groups.extend(rule.groups(width, height)?);
+        }
+
+        Ok(groups)
+    }
+
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn mark(&mut self, number: u64) -> bool {
+        match self.positions.get(&number) {
+            Some(cells) => {
+                let marked = &mut self.marked;
+                cells.iter().for_each(|&cell| marked.insert(cell));
+                true
+            },
+            None => false
+        }
     }
 
-    fn check_solution(&self, marked: &HashSet<u64>) -> bool {
+    pub fn mark_cell(&mut self, cell: usize) {
+        self.marked.insert(cell);
+    }
+
+    pub fn reset(&mut self) {
+        self.marked.clear();
+    }
+
+    fn check_solution(&self) -> bool {
         self.groups.iter().any(|group| {
-            group.intersection(&marked).collect::<Vec<&u64>>().len() == 5
+            self.marked.contains_all(group)
         })
     }
 
-    fn score(&self, marked: &HashSet<u64>) -> u64 {
-        self.squares_set().difference(&marked).collect::<Vec<&u64>>().into_iter().sum()
+    fn score(&self) -> u64 {
+        self.squares
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !self.marked.contains(i))
+            .map(|(_, n)| n)
+            .sum()
+    }
+
+    pub fn winning_turn(&self, moves: &[u64]) -> Option<WinningTurn> {
+        let mut board = self.clone();
+        board.reset();
+
+        moves
+            .iter()
+            .enumerate()
+            .find(|(_, &number)| board.mark(number) && board.check_solution())
+            .map(|(draw_index, &number)| WinningTurn { draw_index, number, score: board.score() * number })
+    }
+
+    fn unmarked_cells(&self, number: u64) -> Vec<usize> {
+        self.positions
+            .get(&number)
+            .map_or(vec![], |cells| cells.iter().filter(|&&cell| !self.marked.contains(cell)).cloned().collect())
+    }
+}
+
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.render(RenderStyle::Brackets) {
+            writeln!(f, "{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderStyle {
+    Brackets,
+    Ansi
+}
+
+impl RenderStyle {
+    fn cell(&self, n: u64, digits: usize, marked: bool, winning: bool) -> String {
+        match (self, marked, winning) {
+            (RenderStyle::Brackets, _, true) => format!("<{:>w$}>", n, w = digits),
+            (RenderStyle::Brackets, true, false) => format!("[{:>w$}]", n, w = digits),
+            (RenderStyle::Ansi, _, true) => format!(" \x1b[1;30;43m{:>w$}\x1b[0m ", n, w = digits),
+            (RenderStyle::Ansi, true, false) => format!(" \x1b[1;32m{:>w$}\x1b[0m ", n, w = digits),
+            (_, false, _) => format!(" {:>w$} ", n, w = digits)
+        }
+    }
+}
+
+impl Board {
+    pub fn render(&self, style: RenderStyle) -> Vec<String> {
+        let digits = self.cell_digits();
+        let winning = self.winning_cells();
+
+        self.squares
+            .chunks(self.width)
+            .enumerate()
+            .map(|(i, row)| {
+                row
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &n)| {
+                        let cell = i * self.width + j;
+                        style.cell(n, digits, self.marked.contains(cell), winning.contains(cell))
+                    })
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect()
+    }
+
+    fn render_width(&self) -> usize {
+        self.width * (self.cell_digits() + 3) - 1
+    }
+
+    fn cell_digits(&self) -> usize {
+        self.squares.iter().map(|n| n.to_string().len()).max().unwrap_or(1).max(2)
+    }
+
+    fn winning_cells(&self) -> Cells {
+        self.groups
+            .iter()
+            .filter(|group| self.marked.contains_all(group))
+            .fold(Cells::default(), |mut cells, group| {
+                cells.union_with(group);
+                cells
+            })
+    }
+}
+
+pub fn render_grid(boards: &[Board], style: RenderStyle, columns: usize) -> String {
+    let mut output = String::new();
+
+    for chunk in boards.chunks(columns.max(1)) {
+        let header = chunk
+            .iter()
+            .map(|board| format!("{:<w$}", format!("Board {}", board.id + 1), w = board.render_width()))
+            .collect::<Vec<String>>();
+        output.push_str(&format!("\n{}\n", header.join("    ").trim_end()));
+
+        let rendered = chunk.iter().map(|board| board.render(style)).collect::<Vec<Vec<String>>>();
+        let height = rendered.iter().map(|lines| lines.len()).max().unwrap_or(0);
+
+        for i in 0..height {
+            let line = chunk
+                .iter()
+                .zip(rendered.iter())
+                .map(|(board, lines)| lines.get(i).cloned().unwrap_or_else(|| " ".repeat(board.render_width())))
+                .collect::<Vec<String>>();
+            output.push_str(&format!("{}\n", line.join("    ").trim_end()));
+        }
+    }
+
+    output
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mark {
+    pub board: u64,
+    pub cell: usize
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Win {
+    pub board: u64,
+    pub score: u64
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawEvent {
+    pub turn: usize,
+    pub number: u64,
+    pub marks: Vec<Mark>,
+    pub wins: Vec<Win>
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GameLog {
+    pub width: usize,
+    pub height: usize,
+    #[serde(default = "default_win_rules")]
+    pub rules: Vec<WinRule>,
+    pub boards: Vec<Vec<u64>>,
+    pub events: Vec<DrawEvent>
+}
+
+fn default_win_rules() -> Vec<WinRule> {
+    vec![WinRule::Lines]
+}
+
+impl GameLog {
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub fn from_json(input: &str) -> serde_json::Result<GameLog> {
+        serde_json::from_str(input)
     }
 
-    fn squares_set(&self) -> HashSet<u64> {
-        HashSet::from_iter(self.squares.iter().cloned())
+    pub fn replay(&self) -> Result<Replay<'_>, BingoError> {
+        let boards = self.boards
+            .iter()
+            .enumerate()
+            .map(|(i, squares)| Board::from_squares(i as u64, self.width, self.height, squares.clone())?.with_win_rules(&self.rules))
+            .collect::<Result<Vec<Board>, BingoError>>()?;
+
+        for event in &self.events {
+            if let Some(mark) = event.marks.iter().find(|mark| mark.board as usize >= boards.len() || mark.cell >= self.width * self.height) {
+                return Err(BingoError::InvalidMark { turn: event.turn, board: mark.board, cell: mark.cell });
+            }
+        }
+
+        Ok(Replay { boards, events: &self.events, position: 0, style: RenderStyle::Brackets, columns: 1 })
     }
 }
 
+pub struct Game {
+    moves: Vec<u64>,
+    boards: Vec<Board>
+}
+
+impl Game {
+    pub fn new(input: &ProblemInput) -> Game {
+        let (moves, boards) = input.clone();
+
+        Game { moves, boards }
+    }
+
+    pub fn play(&self) -> GameLog {
+        let mut boards = self.boards.clone();
+        let mut won: Vec<bool> = vec![false; boards.len()];
+        let mut events: Vec<DrawEvent> = vec![];
+
+        boards.iter_mut().for_each(|board| board.reset());
+
+        for (turn, &number) in self.moves.iter().enumerate() {
+            if won.iter().all(|&w| w) {
+                break;
+            }
+
+            let mut marks: Vec<Mark> = vec![];
+            let mut wins: Vec<Win> = vec![];
+
+            for (i, board) in boards.iter_mut().enumerate() {
+                for cell in board.unmarked_cells(number) {
+                    marks.push(Mark { board: board.id, cell });
+                }
+
+                board.mark(number);
+
+                if !won[i] && board.check_solution() {
+                    won[i] = true;
+                    wins.push(Win { board: board.id, score: board.score() * number });
+                }
+            }
+
+            events.push(DrawEvent { turn: turn + 1, number, marks, wins });
+        }
+
+        let (width, height) = self.boards.first().map_or((0, 0), |board| board.dimensions());
+        let rules = self.boards.first().map_or_else(default_win_rules, |board| board.rules.clone());
+
+        GameLog {
+            width,
+            height,
+            rules,
+            boards: self.boards.iter().map(|board| board.squares.clone()).collect(),
+            events
+        }
+    }
+}
+
+pub struct Replay<'a> {
+    boards: Vec<Board>,
+    events: &'a [DrawEvent],
+    position: usize,
+    style: RenderStyle,
+    columns: usize
+}
+
+impl<'a> Replay<'a> {
+    pub fn with_layout(self, style: RenderStyle, columns: usize) -> Replay<'a> {
+        Replay { style, columns, ..self }
+    }
+}
+
+impl<'a> Iterator for Replay<'a> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        let event = self.events.get(self.position)?;
+        self.position += 1;
+
+        for mark in &event.marks {
+            if let Some(board) = self.boards.get_mut(mark.board as usize) {
+                board.mark_cell(mark.cell);
+            }
+        }
+
+        let mut frame = format!("Turn {}: drew {}\n", event.turn, event.number);
+        frame.push_str(&render_grid(&self.boards, self.style, self.columns));
+
+        for win in &event.wins {
+            frame.push_str(&format!("\nBoard {} wins with score {}\n", win.board + 1, win.score));
+        }
+
+        Some(frame)
+    }
+}
+
+pub fn play_back<R: BufRead, W: Write>(replay: Replay<'_>, input: &mut R, output: &mut W) -> io::Result<()> {
+    let mut line = String::new();
+
+    for frame in replay {
+        write!(output, "\x1b[2J\x1b[H{}\n[enter] next, [q] quit ", frame)?;
+        output.flush()?;
+
+        line.clear();
+        if input.read_line(&mut line)? == 0 || line.trim() == "q" {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
 #[aoc_generator(day4)]
-pub fn input_generator(input: &str) -> ProblemInput {
+pub fn input_generator(input: &str) -> Result<ProblemInput, BingoError> {
+    let rules = match env::var(WIN_RULES_VAR) {
+        Ok(value) => parse_win_rules(&value)?,
+        Err(_) => default_win_rules()
+    };
+
+    input_generator_with_rules(input, &rules)
+}
+
+pub fn input_generator_with_rules(input: &str, rules: &[WinRule]) -> Result<ProblemInput, BingoError> {
     let parts: Vec<&str> = input
         .split("\n\n")
+        .filter(|part| !part.trim().is_empty())
         .collect::<Vec<&str>>();
 
-    let (head, tail) = parts.split_at(1);
-    let moves = parse_moves(head.first().unwrap());
+    let (head, tail) = parts.split_at(1.min(parts.len()));
+    let moves = parse_moves(head.first().ok_or(BingoError::MissingBoards)?)?;
     let boards: Vec<Board> = tail
-        .to_vec()
-        .into_iter()
+        .iter()
         .enumerate()
         .map(|(i, board_string)| {
-           Board::from_str(i as u64, board_string)
+           Board::from_str(i as u64, board_string)?.with_win_rules(rules)
         })
-        .collect::<Vec<Board>>();
+        .collect::<Result<Vec<Board>, BingoError>>()?;
 
-    (moves, boards)
+    let expected = boards.first().ok_or(BingoError::MissingBoards)?.dimensions();
+
+    for (i, board) in boards.iter().enumerate() {
+        if board.dimensions() != expected {
+            return Err(BingoError::MismatchedBoard { board: i + 1, expected, found: board.dimensions() });
+        }
+    }
+
+    Ok((moves, boards))
 }
 
-pub fn parse_moves(moves: &str) -> Vec<u64> {
+pub fn parse_moves(moves: &str) -> Result<Vec<u64>, BingoError> {
     moves
+        .trim()
         .split(',')
-        .map(|val| {
-            val.parse::<u64>().unwrap()
-        }).collect::<Vec<u64>>()
+        .map(parse_number)
+        .collect::<Result<Vec<u64>, BingoError>>()
 }
 
+fn parse_number(value: &str) -> Result<u64, BingoError> {
+    value
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| BingoError::InvalidNumber { value: value.trim().to_string() })
+}
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WinningTurn {
+    pub draw_index: usize,
+    pub number: u64,
+    pub score: u64
+}
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardOutcome {
+    pub board: u64,
+    pub win: Option<WinningTurn>
+}
 
-#[aoc(day4, part1)]
-pub fn part1(input: &ProblemInput)  -> u64 {
-    let (moves, boards) = input;
+pub struct Ranking {
+    outcomes: Vec<BoardOutcome>,
+    winners: Vec<usize>
+}
+
+impl Ranking {
+    pub fn new(input: &ProblemInput) -> Ranking {
+        let (moves, boards) = input;
+
+        let outcomes = boards
+            .iter()
+            .map(|board| BoardOutcome { board: board.id, win: board.winning_turn(moves) })
+            .collect::<Vec<BoardOutcome>>();
+
+        let mut winners = (0..outcomes.len())
+            .filter(|&i| outcomes[i].win.is_some())
+            .collect::<Vec<usize>>();
+        winners.sort_by_key(|&i| outcomes[i].win.map(|win| win.draw_index));
+
+        Ranking { outcomes, winners }
+    }
+
+    pub fn outcomes(&self) -> &[BoardOutcome] {
+        &self.outcomes
+    }
+
+    pub fn never_wins(&self) -> Vec<u64> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.win.is_none())
+            .map(|outcome| outcome.board)
+            .collect()
+    }
+
+    pub fn nth(&self, n: usize) -> Option<&BoardOutcome> {
+        self.winners.get(n).map(|&i| &self.outcomes[i])
+    }
+
+    pub fn first(&self) -> Option<&BoardOutcome> {
+        self.nth(0)
+    }
+
+    pub fn last(&self) -> Option<&BoardOutcome> {
+        self.winners.last().map(|&i| &self.outcomes[i])
+    }
+}
 
-    let mut score = 0;
-    let mut marked: HashSet<u64> = HashSet::new();
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TargetOutcome {
+    WinsFirst,
+    WinsLast
+}
 
-    'outer: for i in moves.into_iter() {
-        marked.insert(*i);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PuzzleTarget {
+    pub board: usize,
+    pub outcome: TargetOutcome,
+    pub draw_index: usize
+}
 
-        '_inner: for board in boards.iter() {
-            if board.check_solution(&marked) {
-                score = board.score(&marked) * *i;
-                break 'outer;
+#[derive(Debug, Clone, PartialEq)]
+pub struct PuzzleConfig {
+    pub seed: u64,
+    pub boards: usize,
+    pub width: usize,
+    pub height: usize,
+    pub numbers: u64,
+    pub target: Option<PuzzleTarget>
+}
+
+impl Default for PuzzleConfig {
+    fn default() -> PuzzleConfig {
+        PuzzleConfig { seed: 0, boards: 100, width: 5, height: 5, numbers: 100, target: None }
+    }
+}
+
+impl PuzzleConfig {
+    fn validate(&self) -> Result<(), BingoError> {
+        let invalid = |reason: &str| Err(BingoError::InvalidPuzzleConfig { reason: reason.to_string() });
+        let squares = self.width * self.height;
+
+        if self.boards == 0 || squares == 0 {
+            return invalid("boards must have at least one square");
+        }
+
+        if (self.numbers as usize) < squares {
+            return invalid("not enough numbers to fill a board");
+        }
+
+        if let Some(target) = self.target {
+            if target.board >= self.boards {
+                return invalid("target board does not exist");
+            }
+
+            if self.height == 1 && self.width > 1 {
+                return invalid("single-row boards win on their first marked number");
+            }
+
+            if target.draw_index + 1 < self.width || target.draw_index as u64 >= self.numbers {
+                return invalid("target draw is out of reach");
             }
+
+            if target.outcome == TargetOutcome::WinsLast && self.boards > 1 && target.draw_index < self.width {
+                return invalid("other boards cannot win before the target draw");
+            }
+
+            if (self.numbers as usize) - target.draw_index <= squares - self.width {
+                return invalid("not enough numbers are drawn after the target draw");
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn generate_puzzle(config: &PuzzleConfig) -> Result<String, BingoError> {
+    config.validate()?;
+
+    let mut rng = StdRng::seed_from_u64(config.seed);
+
+    for _ in 0..GENERATOR_ATTEMPTS {
+        let (moves, boards) = generate_candidate(config, &mut rng);
+        let problem: ProblemInput = (
+            moves,
+            boards
+                .into_iter()
+                .enumerate()
+                .map(|(i, squares)| Board::from_squares(i as u64, config.width, config.height, squares))
+                .collect::<Result<Vec<Board>, BingoError>>()?
+        );
+
+        if config.target.is_none_or(|target| meets_target(&problem, target)) {
+            return Ok(format_puzzle(&problem));
         }
     }
 
-    score
+    Err(BingoError::GenerationFailed { attempts: GENERATOR_ATTEMPTS })
 }
 
-#[aoc(day4, part2)]
-pub fn part2(input: &ProblemInput)  -> u64 {
-    let (moves, mut boards) = input.clone();
+fn generate_candidate(config: &PuzzleConfig, rng: &mut StdRng) -> (Vec<u64>, Vec<Vec<u64>>) {
+    let squares = config.width * config.height;
+    let mut moves = (0..config.numbers).collect::<Vec<u64>>();
+    moves.shuffle(rng);
 
-    let mut score = 0;
-    let mut marked: HashSet<u64> = HashSet::new();
+    let boards = (0..config.boards)
+        .map(|i| match config.target {
+            Some(target) if i == target.board => {
+                seeded_board(config, rng, &moves[..target.draw_index], moves[target.draw_index], &moves[target.draw_index + 1..])
+            },
+            Some(target) if target.outcome == TargetOutcome::WinsLast => {
+                let last = rng.gen_range(config.width - 1..target.draw_index);
+                seeded_board(config, rng, &moves[..last], moves[last], &moves[last + 1..])
+            },
+            _ => moves.choose_multiple(rng, squares).cloned().collect()
+        })
+        .collect::<Vec<Vec<u64>>>();
 
-    'outer: for i in moves.into_iter() {
-        marked.insert(i);
+    (moves, boards)
+}
 
-        let mut solved_board_ids: Vec<u64> = vec![];
+fn seeded_board(config: &PuzzleConfig, rng: &mut StdRng, before: &[u64], winning: u64, after: &[u64]) -> Vec<u64> {
+    let mut row = before.choose_multiple(rng, config.width - 1).cloned().collect::<Vec<u64>>();
+    row.push(winning);
+    row.shuffle(rng);
 
-        '_inner: for board in boards.clone().iter() {
-            if board.check_solution(&marked) {
-                if boards.len() == 1 {
-                    score = board.score(&marked) * i;
-                    break 'outer;
-                } else {
-                    solved_board_ids.push(board.id);
-                }
-            }
+    let mut rest = after
+        .choose_multiple(rng, config.width * (config.height - 1))
+        .cloned()
+        .collect::<Vec<u64>>();
+    let row_index = rng.gen_range(0..config.height) * config.width;
+    rest.splice(row_index..row_index, row);
 
-            boards.retain(|elem| {
-                !solved_board_ids.contains(&elem.id)
-            })
+    rest
+}
+
+fn meets_target(problem: &ProblemInput, target: PuzzleTarget) -> bool {
+    let ranking = Ranking::new(problem);
+    let board = target.board as u64;
+    let draw_index = |outcome: &BoardOutcome| outcome.win.map(|win| win.draw_index);
+    let others = ranking
+        .outcomes()
+        .iter()
+        .filter(|outcome| outcome.board != board)
+        .map(draw_index)
+        .collect::<Vec<Option<usize>>>();
+
+    if draw_index(&ranking.outcomes()[target.board]) != Some(target.draw_index) {
+        return false;
+    }
+
+    match target.outcome {
+        TargetOutcome::WinsFirst => others.iter().all(|other| other.is_none_or(|i| i > target.draw_index)),
+        TargetOutcome::WinsLast => others.iter().all(|other| other.is_some_and(|i| i < target.draw_index))
+    }
+}
+
+fn format_puzzle(problem: &ProblemInput) -> String {
+    let (moves, boards) = problem;
+    let mut output = moves.iter().map(u64::to_string).collect::<Vec<String>>().join(",");
+
+    for board in boards {
+        output.push('\n');
+
+        for row in board.squares.chunks(board.width) {
+            output.push('\n');
+            output.push_str(&row.iter().map(|n| format!("{:>2}", n)).collect::<Vec<String>>().join(" "));
         }
+    }
+
+    output.push('\n');
+    output
+}
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiggedDraw {
+    pub moves: Vec<u64>,
+    pub target_draw: usize,
+    pub last_draw: usize
+}
 
+impl RiggedDraw {
+    pub fn gap(&self) -> usize {
+        self.last_draw - self.target_draw
     }
+}
 
-    score
+impl Board {
+    fn group_numbers(&self, group: &Cells) -> Vec<u64> {
+        let mut numbers = self.squares
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| group.contains(i))
+            .map(|(_, &n)| n)
+            .collect::<Vec<u64>>();
+        numbers.sort_unstable();
+        numbers.dedup();
+
+        numbers
+    }
+
+    fn completed_by(&self, numbers: &HashSet<u64>) -> bool {
+        self.groups
+            .iter()
+            .any(|group| self.group_numbers(group).iter().all(|n| numbers.contains(n)))
+    }
+}
+
+fn first_win_candidates(boards: &[Board], target: usize) -> Vec<Vec<u64>> {
+    let board = &boards[target];
+    let mut candidates = board.groups
+        .iter()
+        .map(|group| board.group_numbers(group))
+        .filter(|numbers| {
+            let drawn: HashSet<u64> = numbers.iter().cloned().collect();
+
+            boards
+                .iter()
+                .enumerate()
+                .all(|(i, other)| i == target || !other.completed_by(&drawn))
+        })
+        .collect::<Vec<Vec<u64>>>();
+
+    candidates.sort_by_key(|numbers| numbers.len());
+    candidates.dedup();
+
+    candidates
+}
+
+fn rigged_outcome(boards: &[Board], target: usize, moves: Vec<u64>) -> Option<RiggedDraw> {
+    let ranking = Ranking::new(&(moves.clone(), boards.to_vec()));
+    let target_draw = ranking.outcomes()[target].win?.draw_index;
+    let first = ranking.first()?;
+
+    if first.board != boards[target].id || ranking.nth(1).and_then(|outcome| outcome.win).is_some_and(|win| win.draw_index == target_draw) {
+        return None;
+    }
+
+    let last_draw = ranking.last()?.win?.draw_index;
+
+    Some(RiggedDraw { moves, target_draw, last_draw })
+}
+
+pub fn rig_first_win(boards: &[Board], target: usize) -> Option<RiggedDraw> {
+    if target >= boards.len() {
+        return None;
+    }
+
+    first_win_candidates(boards, target)
+        .into_iter()
+        .find_map(|moves| rigged_outcome(boards, target, moves))
+}
+
+fn greedy_hitting_set(groups: &[Vec<u64>]) -> Vec<u64> {
+    let mut open = groups.iter().filter(|group| !group.is_empty()).collect::<Vec<&Vec<u64>>>();
+    let mut chosen: Vec<u64> = vec![];
+
+    while !open.is_empty() {
+        let mut counts: HashMap<u64, usize> = HashMap::new();
+
+        for &n in open.iter().flat_map(|group| group.iter()) {
+            *counts.entry(n).or_insert(0) += 1;
+        }
+
+        let best = counts
+            .into_iter()
+            .max_by_key(|&(n, count)| (count, Reverse(n)))
+            .map(|(n, _)| n)
+            .unwrap();
+
+        chosen.push(best);
+        open.retain(|group| !group.contains(&best));
+    }
+
+    chosen
+}
+
+/// Heuristic: lets the target win as early as possible, then holds back a greedy (not
+/// necessarily minimal) hitting set of one other board's groups until the end of the draw.
+/// The returned gap is the widest found across those choices, not a proven maximum.
+pub fn rig_widest_gap(boards: &[Board], target: usize) -> Option<RiggedDraw> {
+    if target >= boards.len() {
+        return None;
+    }
+
+    let mut pool = boards.iter().flat_map(|board| board.squares.iter().cloned()).collect::<Vec<u64>>();
+    pool.sort_unstable();
+    pool.dedup();
+
+    let mut best: Option<RiggedDraw> = None;
+
+    for first in first_win_candidates(boards, target) {
+        for (i, other) in boards.iter().enumerate() {
+            if i == target && boards.len() > 1 {
+                continue;
+            }
+
+            let groups = other.groups
+                .iter()
+                .map(|group| other.group_numbers(group).into_iter().filter(|n| !first.contains(n)).collect())
+                .collect::<Vec<Vec<u64>>>();
+
+            let hitting = greedy_hitting_set(&groups);
+
+            let mut moves = first.clone();
+            moves.extend(pool.iter().filter(|n| !first.contains(n) && !hitting.contains(n)));
+            moves.extend(hitting);
+
+            if let Some(rigged) = rigged_outcome(boards, target, moves) {
+                if best.as_ref().is_none_or(|best| rigged.gap() > best.gap()) {
+                    best = Some(rigged);
+                }
+            }
+        }
+    }
+
+    best
+}
+
+#[aoc(day4, part1)]
+pub fn part1(input: &ProblemInput)  -> u64 {
+    Ranking::new(input)
+        .first()
+        .and_then(|outcome| outcome.win)
+        .map_or(0, |win| win.score)
+}
+
+#[aoc(day4, part2)]
+pub fn part2(input: &ProblemInput)  -> u64 {
+    Ranking::new(input)
+        .last()
+        .and_then(|outcome| outcome.win)
+        .map_or(0, |win| win.score)
 }
 
 #[cfg(test)]
@@ -176,7 +1042,7 @@ mod tests {
             22 11 13  6  5
              2  0 12  3  7";
 
-        assert_eq!(part1(&input_generator(input)), 4512)
+        assert_eq!(part1(&input_generator_with_rules(input, &default_win_rules()).unwrap()), 4512)
     }
 
     #[test]
@@ -202,38 +1068,446 @@ mod tests {
             22 11 13  6  5
              2  0 12  3  7";
 
-        assert_eq!(part2(&input_generator(input)), 1924)
+        assert_eq!(part2(&input_generator_with_rules(input, &default_win_rules()).unwrap()), 1924)
     }
 
     #[test]
     fn test_parse_board() {
         let input = "            22 13 17 11  0\n             8  2 23  4 24\n            21  9 14 16  7\n             6 10  3 18  5\n             1 12 20 15 19";
 
-        let board = Board::from_str(1, input);
+        let board = Board::from_str(1, input).unwrap();
         assert_eq!(board.squares, vec![22, 13, 17, 11, 0, 8, 2, 23, 4, 24, 21, 9, 14, 16, 7, 6, 10, 3, 18, 5, 1, 12, 20, 15, 19])
     }
 
     #[test]
     fn test_board_groups() {
-        let board = Board::from_str(1, "            22 13 17 11  0\n             8  2 23  4 24\n            21  9 14 16  7\n             6 10  3 18  5\n             1 12 20 15 19");
+        let board = Board::from_str(1, "            22 13 17 11  0\n             8  2 23  4 24\n            21  9 14 16  7\n             6 10  3 18  5\n             1 12 20 15 19").unwrap();
 
         assert_eq!(board.groups.len(), 10)
     }
 
     #[test]
     fn test_board_check_solution() {
-        let marked: HashSet<u64> = HashSet::from_iter(vec![22, 13, 17, 11, 0]);
-        let board = Board::from_str(1, "            22 13 17 11  0\n             8  2 23  4 24\n            21  9 14 16  7\n             6 10  3 18  5\n             1 12 20 15 19");
+        let marked: Vec<u64> = vec![22, 13, 17, 11, 0];
+        let mut board = Board::from_str(1, "            22 13 17 11  0\n             8  2 23  4 24\n            21  9 14 16  7\n             6 10  3 18  5\n             1 12 20 15 19").unwrap();
+
+        marked.into_iter().for_each(|n| { board.mark(n); });
 
-        assert_eq!(board.check_solution(&marked), true)
+        assert!(board.check_solution())
 
     }
 
     #[test]
     fn test_board_score() {
-        let marked: HashSet<u64> = HashSet::from_iter(vec![22, 13, 17, 11, 0, 8, 2, 23, 4, 24, 21, 9, 14, 16, 7, 6, 10, 3, 18, 5, 1, 12]);
-        let board = Board::from_str(1, "            22 13 17 11  0\n             8  2 23  4 24\n            21  9 14 16  7\n             6 10  3 18  5\n             1 12 20 15 19");
+        let marked: Vec<u64> = vec![22, 13, 17, 11, 0, 8, 2, 23, 4, 24, 21, 9, 14, 16, 7, 6, 10, 3, 18, 5, 1, 12];
+        let mut board = Board::from_str(1, "            22 13 17 11  0\n             8  2 23  4 24\n            21  9 14 16  7\n             6 10  3 18  5\n             1 12 20 15 19").unwrap();
+
+        marked.into_iter().for_each(|n| { board.mark(n); });
+
+        assert_eq!(board.score(), 54)
+    }
+
+    #[test]
+    fn test_rectangular_boards() {
+        let input = "1,4,5,8,7,9\n\n1 2 3\n4 5 6\n\n7 8 9\n1 5 6";
+        let (moves, boards) = input_generator_with_rules(input, &default_win_rules()).unwrap();
+
+        assert_eq!(boards[0].dimensions(), (3, 2));
+        assert_eq!(boards[0].groups.len(), 5);
+        assert_eq!(part1(&(moves.clone(), boards.clone())), 16 * 4);
+        assert_eq!(part2(&(moves, boards)), 22 * 8)
+    }
+
+    #[test]
+    fn test_board_column_solution() {
+        let mut board = Board::from_str(1, "1 2 3\n4 5 6\n7 8 9").unwrap();
+
+        assert!(!board.mark(10));
+        assert!(board.mark(2) && board.mark(5));
+        assert!(!board.check_solution());
+        assert!(board.mark(8));
+        assert!(board.check_solution());
+
+        board.reset();
+        assert!(!board.check_solution());
+        assert_eq!(board.score(), 45)
+    }
+
+    #[test]
+    fn test_large_board() {
+        let input = (0..12).map(|i| (0..11).map(|j| (i * 11 + j).to_string()).collect::<Vec<String>>().join(" ")).collect::<Vec<String>>().join("\n");
+        let mut board = Board::from_str(0, &input).unwrap();
+
+        assert_eq!(board.dimensions(), (11, 12));
+        (121..131).for_each(|n| { board.mark(n); });
+        assert!(!board.check_solution());
+        assert!(board.mark(131));
+        assert!(board.check_solution());
+        assert_eq!(board.score(), (0..121).sum::<u64>());
+
+        let moves = (0..132).rev().collect::<Vec<u64>>();
+        let blackout = Board::from_str(0, &input).unwrap().with_win_rules(&[WinRule::Blackout]).unwrap();
+        assert_eq!(blackout.winning_turn(&moves), Some(WinningTurn { draw_index: 131, number: 0, score: 0 }))
+    }
+
+    #[test]
+    fn test_win_rules() {
+        let board = Board::from_str(1, "1 2 3\n4 5 6\n7 8 9").unwrap();
+
+        let mut diagonal = board.clone().with_win_rules(&[WinRule::Lines, WinRule::Diagonals]).unwrap();
+        assert_eq!(diagonal.groups.len(), 8);
+        vec![3, 5, 7].into_iter().for_each(|n| { diagonal.mark(n); });
+        assert!(diagonal.check_solution());
+
+        let mut corners = board.clone().with_win_rules(&[WinRule::FourCorners]).unwrap();
+        vec![1, 2, 3, 7, 9].into_iter().for_each(|n| { corners.mark(n); });
+        assert!(corners.check_solution());
+        assert_eq!(corners.score(), 4 + 5 + 6 + 8);
+
+        let mut blackout = board.with_win_rules(&[WinRule::Blackout]).unwrap();
+        (1..9).for_each(|n| { blackout.mark(n); });
+        assert!(!blackout.check_solution());
+        blackout.mark(9);
+        assert!(blackout.check_solution())
+    }
+
+    #[test]
+    fn test_parse_win_rules() {
+        assert_eq!(parse_win_rules("lines, diagonals"), Ok(vec![WinRule::Lines, WinRule::Diagonals]));
+        assert_eq!(parse_win_rules("corners,blackout"), Ok(vec![WinRule::FourCorners, WinRule::Blackout]));
+        assert_eq!(parse_win_rules("stars"), Err(BingoError::UnknownWinRule { value: "stars".to_string() }))
+    }
+
+    #[test]
+    fn test_diagonals_need_square_boards() {
+        let input = "1,2,3\n\n1 2 3\n4 5 6";
+
+        assert_eq!(
+            input_generator_with_rules(input, &[WinRule::Diagonals]).err(),
+            Some(BingoError::UnsupportedWinRule { rule: WinRule::Diagonals, width: 3, height: 2 })
+        )
+    }
+
+    #[test]
+    fn test_ranking() {
+        let input = "1,4,5,8,7,9\n\n1 2 3\n4 5 6\n\n7 8 9\n1 5 6\n\n10 11 12\n13 14 15";
+        let ranking = Ranking::new(&input_generator_with_rules(input, &default_win_rules()).unwrap());
 
-        assert_eq!(board.score(&marked), 54)
+        assert_eq!(ranking.outcomes().len(), 3);
+        assert_eq!(ranking.first().unwrap().win, Some(WinningTurn { draw_index: 1, number: 4, score: 64 }));
+        assert_eq!(ranking.last().unwrap().board, 1);
+        assert_eq!(ranking.nth(1), ranking.last());
+        assert_eq!(ranking.nth(2), None);
+        assert_eq!(ranking.never_wins(), vec![2])
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_game_log() {
+        let input = "1,4,5,8,7,9\n\n1 2 3\n4 5 6\n\n7 8 9\n1 5 6";
+        let log = Game::new(&input_generator_with_rules(input, &default_win_rules()).unwrap()).play();
+
+        assert_eq!(log.events.len(), 4);
+        assert_eq!(log.events[0], DrawEvent {
+            turn: 1,
+            number: 1,
+            marks: vec![Mark { board: 0, cell: 0 }, Mark { board: 1, cell: 3 }],
+            wins: vec![]
+        });
+        assert_eq!(log.events[1].wins, vec![Win { board: 0, score: 16 * 4 }]);
+        assert_eq!(log.events[3].wins, vec![Win { board: 1, score: 22 * 8 }])
+    }
+
+    #[test]
+    fn test_game_log_json_replay() {
+        let input = "1,4,5,8,7,9\n\n1 2 3\n4 5 6\n\n7 8 9\n1 5 6";
+        let log = Game::new(&input_generator_with_rules(input, &default_win_rules()).unwrap()).play();
+        let restored = GameLog::from_json(&log.to_json().unwrap()).unwrap();
+
+        assert_eq!(restored, log);
+
+        let frames = restored.replay().unwrap().collect::<Vec<String>>();
+        assert_eq!(frames.len(), 4);
+        assert!(frames[1].starts_with("Turn 2: drew 4\n\nBoard 1\n< 1>   2    3\n< 4>   5    6\n"));
+        assert!(frames[1].ends_with("Board 1 wins with score 64\n"));
+
+        let mut output: Vec<u8> = vec![];
+        play_back(restored.replay().unwrap(), &mut "\nq\n".as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap().matches("Turn ").count(), 2)
+    }
+
+    #[test]
+    fn test_replay_win_rules() {
+        let input = "5,1,9\n\n1 2 3\n4 5 6\n7 8 9";
+        let log = Game::new(&input_generator_with_rules(input, &[WinRule::Diagonals]).unwrap()).play();
+        let restored = GameLog::from_json(&log.to_json().unwrap()).unwrap();
+
+        assert_eq!(restored.rules, vec![WinRule::Diagonals]);
+
+        let frames = restored.replay().unwrap().collect::<Vec<String>>();
+        assert!(frames[2].contains("< 1>   2    3\n  4  < 5>   6\n  7    8  < 9>\n"));
+        assert!(frames[2].ends_with("Board 1 wins with score 270\n"));
+
+        let legacy = log.to_json().unwrap().replace("\"rules\": [\n    \"Diagonals\"\n  ],", "");
+        assert_eq!(GameLog::from_json(&legacy).unwrap().rules, vec![WinRule::Lines])
+    }
+
+    #[test]
+    fn test_replay_invalid_marks() {
+        let log = |board: u64, cell: usize| GameLog {
+            width: 2,
+            height: 1,
+            rules: default_win_rules(),
+            boards: vec![vec![1, 2]],
+            events: vec![DrawEvent { turn: 1, number: 1, marks: vec![Mark { board, cell }], wins: vec![] }]
+        };
+
+        assert_eq!(log(0, 200).replay().err(), Some(BingoError::InvalidMark { turn: 1, board: 0, cell: 200 }));
+        assert_eq!(log(0, 2).replay().err(), Some(BingoError::InvalidMark { turn: 1, board: 0, cell: 2 }));
+        assert_eq!(log(1, 0).replay().err(), Some(BingoError::InvalidMark { turn: 1, board: 1, cell: 0 }));
+        assert_eq!(log(0, 1).replay().unwrap().count(), 1)
+    }
+
+    #[test]
+    fn test_render_board() {
+        let mut board = Board::from_str(0, "1 2 3\n4 5 6\n7 8 100").unwrap();
+        vec![1, 2, 3, 5].into_iter().for_each(|n| { board.mark(n); });
+
+        assert_eq!(board.to_string(), "<  1> <  2> <  3>\n   4  [  5]    6 \n   7     8   100 \n");
+        assert_eq!(board.render(RenderStyle::Ansi)[1], "   4   \x1b[1;32m  5\x1b[0m     6 ")
+    }
+
+    #[test]
+    fn test_render_grid() {
+        let (_, mut boards) = input_generator_with_rules("1,4\n\n1 2\n3 4\n\n5 6\n7 8\n\n1 3\n2 9", &default_win_rules()).unwrap();
+        boards.iter_mut().for_each(|board| { board.mark(1); });
+
+        assert_eq!(
+            render_grid(&boards, RenderStyle::Brackets, 2),
+            "\nBoard 1      Board 2\n[ 1]   2       5    6\n  3    4       7    8\n\nBoard 3\n[ 1]   3\n  2    9\n"
+        )
+    }
+
+    #[test]
+    fn test_ragged_board() {
+        let input = "1,2,3\n\n1 2 3\n4 5\n";
+
+        assert_eq!(input_generator_with_rules(input, &default_win_rules()).err(), Some(BingoError::RaggedBoard { board: 1, row: 2, expected: 3, found: 2 }))
+    }
+
+    #[test]
+    fn test_mismatched_boards() {
+        let input = "1,2,3\n\n1 2\n3 4\n\n1 2 3\n4 5 6\n7 8 9";
+
+        assert_eq!(input_generator_with_rules(input, &default_win_rules()).err(), Some(BingoError::MismatchedBoard { board: 2, expected: (2, 2), found: (3, 3) }))
+    }
+
+    #[test]
+    fn test_duplicate_numbers_on_board() {
+        let mut board = Board::from_str(1, "1 2 1\n3 4 5\n1 6 7").unwrap();
+
+        assert_eq!(board.positions[&1], vec![0, 2, 6]);
+        board.mark(1);
+        assert!(!board.check_solution());
+        assert_eq!(board.score(), 2 + 3 + 4 + 5 + 6 + 7);
+        board.mark(2);
+        assert!(board.check_solution());
+        assert_eq!(board.score(), 3 + 4 + 5 + 6 + 7)
+    }
+
+    #[test]
+    fn test_repeated_draws() {
+        let input = "5,5,1,5,2,3\n\n1 2 3\n4 5 6\n\n5 5 9\n8 7 6";
+        let problem = input_generator_with_rules(input, &default_win_rules()).unwrap();
+        let log = Game::new(&problem).play();
+
+        assert_eq!(log.events[0].marks, vec![Mark { board: 0, cell: 4 }, Mark { board: 1, cell: 0 }, Mark { board: 1, cell: 1 }]);
+        assert_eq!(log.events[1].marks, vec![]);
+        assert_eq!(log.events[3].marks, vec![]);
+        assert_eq!(part1(&problem), 13 * 2);
+        assert_eq!(Ranking::new(&problem).first().unwrap().board, 0);
+        assert_eq!(Ranking::new(&problem).never_wins(), vec![1])
+    }
+
+    fn reference_outcome(width: usize, height: usize, squares: &[u64], moves: &[u64]) -> Option<WinningTurn> {
+        let mut marked = vec![false; squares.len()];
+
+        for (draw_index, &number) in moves.iter().enumerate() {
+            for (i, &n) in squares.iter().enumerate() {
+                if n == number {
+                    marked[i] = true;
+                }
+            }
+
+            let row_won = (0..height).any(|i| (0..width).all(|j| marked[i * width + j]));
+            let column_won = (0..width).any(|j| (0..height).all(|i| marked[i * width + j]));
+
+            if row_won || column_won {
+                let unmarked: u64 = squares.iter().zip(marked.iter()).filter(|(_, &m)| !m).map(|(n, _)| n).sum();
+
+                return Some(WinningTurn { draw_index, number, score: unmarked * number });
+            }
+        }
+
+        None
+    }
+
+    #[test]
+    fn test_matches_reference_simulator() {
+        let mut rng = StdRng::seed_from_u64(0x9e37_79b9_7f4a_7c15);
+
+        for _ in 0..300 {
+            let width = rng.gen_range(1..=5);
+            let height = rng.gen_range(1..=5);
+            let range = rng.gen_range(1..=30);
+            let moves = (0..rng.gen_range(0..40)).map(|_| rng.gen_range(0..range)).collect::<Vec<u64>>();
+            let boards = (0..rng.gen_range(1..=6))
+                .map(|_| (0..width * height).map(|_| rng.gen_range(0..range)).collect::<Vec<u64>>())
+                .collect::<Vec<Vec<u64>>>();
+
+            let problem: ProblemInput = (
+                moves.clone(),
+                boards
+                    .iter()
+                    .enumerate()
+                    .map(|(i, squares)| Board::from_squares(i as u64, width, height, squares.clone()).unwrap())
+                    .collect()
+            );
+
+            let ranking = Ranking::new(&problem);
+            let expected = boards
+                .iter()
+                .map(|squares| reference_outcome(width, height, squares, &moves))
+                .collect::<Vec<Option<WinningTurn>>>();
+
+            assert_eq!(ranking.outcomes().iter().map(|outcome| outcome.win).collect::<Vec<Option<WinningTurn>>>(), expected);
+
+            let first = expected.iter().flatten().min_by_key(|win| win.draw_index);
+            let last = expected.iter().flatten().max_by_key(|win| win.draw_index);
+            assert_eq!(part1(&problem), first.map_or(0, |win| win.score));
+            assert_eq!(part2(&problem), last.map_or(0, |win| win.score));
+        }
+    }
+
+    #[test]
+    fn test_generate_puzzle() {
+        let config = PuzzleConfig { seed: 7, boards: 10, ..PuzzleConfig::default() };
+        let puzzle = generate_puzzle(&config).unwrap();
+        let (moves, boards) = input_generator_with_rules(&puzzle, &default_win_rules()).unwrap();
+
+        assert_eq!(generate_puzzle(&config).unwrap(), puzzle);
+        assert_ne!(generate_puzzle(&PuzzleConfig { seed: 8, ..config }).unwrap(), puzzle);
+        assert_eq!(moves.len(), 100);
+        assert_eq!(boards.len(), 10);
+        assert!(boards.iter().all(|board| board.dimensions() == (5, 5)))
+    }
+
+    #[test]
+    fn test_generate_puzzle_targets() {
+        for seed in 0..20 {
+            let first = PuzzleTarget { board: 3, outcome: TargetOutcome::WinsFirst, draw_index: 6 };
+            let config = PuzzleConfig { seed, boards: 8, target: Some(first), ..PuzzleConfig::default() };
+            let problem = input_generator_with_rules(&generate_puzzle(&config).unwrap(), &default_win_rules()).unwrap();
+            let ranking = Ranking::new(&problem);
+
+            assert_eq!(ranking.first().unwrap().board, 3);
+            assert_eq!(ranking.first().unwrap().win.unwrap().draw_index, 6);
+            assert_eq!(part1(&problem), ranking.first().unwrap().win.unwrap().score);
+
+            let last = PuzzleTarget { board: 0, outcome: TargetOutcome::WinsLast, draw_index: 40 };
+            let config = PuzzleConfig { seed, boards: 8, target: Some(last), ..PuzzleConfig::default() };
+            let problem = input_generator_with_rules(&generate_puzzle(&config).unwrap(), &default_win_rules()).unwrap();
+            let ranking = Ranking::new(&problem);
+
+            assert_eq!(ranking.last().unwrap().board, 0);
+            assert_eq!(ranking.last().unwrap().win.unwrap().draw_index, 40);
+            assert!(ranking.never_wins().is_empty());
+            assert_eq!(part2(&problem), ranking.last().unwrap().win.unwrap().score)
+        }
+    }
+
+    #[test]
+    fn test_generate_puzzle_invalid_config() {
+        let target = PuzzleTarget { board: 0, outcome: TargetOutcome::WinsFirst, draw_index: 2 };
+        let config = PuzzleConfig { target: Some(target), ..PuzzleConfig::default() };
+
+        assert_eq!(
+            generate_puzzle(&config),
+            Err(BingoError::InvalidPuzzleConfig { reason: "target draw is out of reach".to_string() })
+        );
+
+        let target = PuzzleTarget { board: 0, outcome: TargetOutcome::WinsFirst, draw_index: 4 };
+        let config = PuzzleConfig { height: 1, numbers: 30, target: Some(target), ..PuzzleConfig::default() };
+        assert_eq!(
+            generate_puzzle(&config),
+            Err(BingoError::InvalidPuzzleConfig { reason: "single-row boards win on their first marked number".to_string() })
+        );
+
+        let target = PuzzleTarget { board: 0, outcome: TargetOutcome::WinsFirst, draw_index: 4 };
+        let config = PuzzleConfig { boards: 3, width: 1, height: 1, numbers: 30, target: Some(target), ..PuzzleConfig::default() };
+        assert!(generate_puzzle(&config).is_ok())
+    }
+
+    #[test]
+    fn test_rig_first_win() {
+        let (_, boards) = input_generator_with_rules("1\n\n1 2 3\n4 5 6\n\n7 8 9\n1 2 6\n\n4 5 9\n1 2 3\n\n6 5 4\n3 2 1", &default_win_rules()).unwrap();
+
+        assert_eq!(rig_first_win(&boards, 1).unwrap(), RiggedDraw { moves: vec![1, 7], target_draw: 1, last_draw: 1 });
+        assert_eq!(rig_first_win(&boards, 2).unwrap().moves, vec![3, 9]);
+        assert_eq!(rig_first_win(&boards, 0), None);
+        assert_eq!(rig_first_win(&boards, 3), None)
+    }
+
+    #[test]
+    fn test_rig_widest_gap() {
+        let input = "\
+            7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+
+            22 13 17 11  0
+             8  2 23  4 24
+            21  9 14 16  7
+             6 10  3 18  5
+             1 12 20 15 19
+
+             3 15  0  2 22
+             9 18 13 17  5
+            19  8  7 25 23
+            20 11 10 24  4
+            14 21 16 12  6
+
+            14 21 17 24  4
+            10 16 15  9 19
+            18  8 23 26 20
+            22 11 13  6  5
+             2  0 12  3  7";
+        let (_, boards) = input_generator_with_rules(input, &default_win_rules()).unwrap();
+
+        let fastest = rig_first_win(&boards, 1).unwrap();
+        assert_eq!(fastest.target_draw, 4);
+
+        let rigged = rig_widest_gap(&boards, 1).unwrap();
+        let ranking = Ranking::new(&(rigged.moves.clone(), boards.clone()));
+        assert_eq!(rigged.target_draw, 4);
+        assert_eq!(ranking.first().unwrap().board, 1);
+        assert_eq!(ranking.last().unwrap().win.unwrap().draw_index, rigged.last_draw);
+        assert_eq!(rigged.gap(), 18)
+    }
+
+    #[test]
+    fn test_rig_widest_gap_large_boards() {
+        let mut rng = StdRng::seed_from_u64(39);
+        let mut numbers = (0..300).collect::<Vec<u64>>();
+        let boards = (0..3)
+            .map(|i| {
+                numbers.shuffle(&mut rng);
+                Board::from_squares(i, 10, 10, numbers[..100].to_vec()).unwrap()
+            })
+            .collect::<Vec<Board>>();
+
+        let rigged = rig_widest_gap(&boards, 0).unwrap();
+        let ranking = Ranking::new(&(rigged.moves.clone(), boards.clone()));
+
+        assert_eq!(rigged.target_draw, 9);
+        assert_eq!(ranking.first().unwrap().board, 0);
+        assert!(rigged.gap() > 0)
+    }
+}
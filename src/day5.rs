@@ -1,4 +1,8 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
 use itertools::zip;
 
 #[derive(Debug)]
@@ -7,146 +11,1151 @@ enum Direction {
     Vertical
 }
 
-#[derive(PartialEq, Eq, Hash, Debug, Clone)]
-pub struct Point(usize, usize);
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Rasterization {
+    Diagonal,
+    Lattice,
+    Bresenham
+}
+
+#[derive(Debug, PartialEq)]
+pub enum VentError {
+    UnsupportedSlope { line: usize, start: Point, end: Point },
+    UnsupportedSlope3d { line: usize, start: Voxel, end: Voxel },
+    OutOfRange { line: usize, limit: i64 },
+    TooLong { line: usize, points: i64, limit: i64 },
+    TooManyPoints { lines: usize, points: i64, limit: i64 },
+    InvalidLine { line: usize, text: String },
+    InvalidCoordinate { line: usize, value: String }
+}
+
+impl fmt::Display for VentError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VentError::UnsupportedSlope { line, start, end } => {
+                write!(f, "line {} ({},{} -> {},{}) is not horizontal, vertical or diagonal", line, start.0, start.1, end.0, end.1)
+            },
+            VentError::UnsupportedSlope3d { line, start, end } => {
+                write!(f, "line {} ({},{},{} -> {},{},{}) is not axis-aligned or diagonal", line, start.0, start.1, start.2, end.0, end.1, end.2)
+            },
+            VentError::OutOfRange { line, limit } => write!(f, "line {} has a coordinate outside of +/-{}", line, limit),
+            VentError::TooLong { line, points, limit } => write!(f, "line {} covers {} points, more than the limit of {}", line, points, limit),
+            VentError::TooManyPoints { lines, points, limit } => {
+                write!(f, "lines 1 to {} cover {} points in total, more than the limit of {}", lines, points, limit)
+            },
+            VentError::InvalidLine { line, text } => write!(f, "line {} ({:?}) is not of the form x,y -> x,y or x,y,z -> x,y,z", line, text),
+            VentError::InvalidCoordinate { line, value } => write!(f, "line {} has an invalid coordinate {:?}", line, value)
+        }
+    }
+}
+
+impl Error for VentError {}
+
+const MAX_COORDINATE: i64 = 1 << 40;
+const MAX_LINE_POINTS: i64 = 1 << 26;
+const MAX_TOTAL_POINTS: i64 = 1 << 28;
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Point(i64, i64);
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    start: Point,
+    end: Point,
+    number: usize
+}
+
+impl Line {
+    fn is_straight(&self) -> bool {
+       self.start.0 == self.end.0 || self.start.1 == self.end.1
+    }
+
+    fn is_diagonal(&self) -> bool {
+        (self.end.0 - self.start.0).abs() == (self.end.1 - self.start.1).abs()
+    }
+
+    pub fn rasterize(&self, mode: Rasterization, line: usize) -> Result<Vec<Point>, VentError> {
+        self.check_length(mode, line)?;
+
+        match mode {
+            Rasterization::Diagonal if self.is_straight() || self.is_diagonal() => Ok(self.intersecting_points()),
+            Rasterization::Diagonal => Err(VentError::UnsupportedSlope { line, start: self.start, end: self.end }),
+            Rasterization::Lattice => Ok(self.lattice_points()),
+            Rasterization::Bresenham => Ok(self.bresenham_points())
+        }
+    }
+
+    fn intersecting_points(&self) -> Vec<Point> {
+        let range_x: Vec<i64> = Line::generate_range(self.start.0, self.end.0);
+        let range_y: Vec<i64> = Line::generate_range(self.start.1, self.end.1);
+
+        match (range_x.len(), range_y.len()) {
+            (1, _) => Line::expand(range_y, self.start.0, Direction::Horizontal),
+            (_, 1) => Line::expand(range_x, self.start.1, Direction::Vertical),
+            (_, _) => Line::expand_diagonal(range_x, range_y)
+        }
+    }
+
+    fn lattice_points(&self) -> Vec<Point> {
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let steps = gcd(dx.abs(), dy.abs());
+
+        if steps == 0 {
+            return vec![self.start];
+        }
+
+        let (sx, sy) = (dx / steps, dy / steps);
+
+        (0..=steps)
+            .map(|i| Point(self.start.0 + i * sx, self.start.1 + i * sy))
+            .collect()
+    }
+
+    fn walk<F: FnMut(Point)>(&self, mode: Rasterization, line: usize, mut visit: F) -> Result<(), VentError> {
+        self.check_length(mode, line)?;
+
+        if mode == Rasterization::Bresenham {
+            self.bresenham_points().into_iter().for_each(visit);
+            return Ok(());
+        }
+
+        if mode == Rasterization::Diagonal && !self.is_straight() && !self.is_diagonal() {
+            return Err(VentError::UnsupportedSlope { line, start: self.start, end: self.end });
+        }
+
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let steps = gcd(dx.abs(), dy.abs());
+        let (sx, sy) = match steps {
+            0 => (0, 0),
+            _ => (dx / steps, dy / steps)
+        };
+
+        for i in 0..=steps {
+            visit(Point(self.start.0 + i * sx, self.start.1 + i * sy));
+        }
+
+        Ok(())
+    }
+
+    pub fn covers(&self, point: Point, mode: Rasterization) -> bool {
+        if mode == Rasterization::Bresenham {
+            return self.bresenham_points().contains(&point);
+        }
+
+        let (dx, dy) = (self.end.0 - self.start.0, self.end.1 - self.start.1);
+        let (px, py) = (point.0 - self.start.0, point.1 - self.start.1);
+        let steps = gcd(dx.abs(), dy.abs());
+
+        if steps == 0 {
+            return point == self.start;
+        }
+
+        let (sx, sy) = (dx / steps, dy / steps);
+        let t = match sx {
+            0 => py / sy,
+            _ => px / sx
+        };
+
+        (0..=steps).contains(&t) && t * sx == px && t * sy == py
+    }
+
+    fn segment(&self, line: usize) -> Result<Segment, VentError> {
+        let (a, b) = match (self.start.0, self.start.1) <= (self.end.0, self.end.1) {
+            true => (self.start, self.end),
+            false => (self.end, self.start)
+        };
+
+        let (family, key, lo, hi) = if a.1 == b.1 {
+            (Family::Horizontal, a.1, a.0, b.0)
+        } else if a.0 == b.0 {
+            (Family::Vertical, a.0, a.1, b.1)
+        } else if b.0 - a.0 == b.1 - a.1 {
+            (Family::Diagonal, a.1 - a.0, a.0, b.0)
+        } else if b.0 - a.0 == a.1 - b.1 {
+            (Family::AntiDiagonal, a.1 + a.0, a.0, b.0)
+        } else {
+            return Err(VentError::UnsupportedSlope { line, start: self.start, end: self.end });
+        };
+
+        Ok(Segment { family, key, lo, hi })
+    }
+
+    fn bresenham_points(&self) -> Vec<Point> {
+        let (dx, dy) = ((self.end.0 - self.start.0).abs(), -(self.end.1 - self.start.1).abs());
+        let (sx, sy) = ((self.end.0 - self.start.0).signum(), (self.end.1 - self.start.1).signum());
+        let mut points: Vec<Point> = vec![];
+        let mut current = self.start;
+        let mut error = dx + dy;
+
+        loop {
+            points.push(current);
+
+            if current == self.end {
+                break;
+            }
+
+            let doubled = 2 * error;
+
+            if doubled >= dy {
+                error += dy;
+                current.0 += sx;
+            }
+
+            if doubled <= dx {
+                error += dx;
+                current.1 += sy;
+            }
+        }
+
+        points
+    }
+
+    fn expand(range: Vec<i64>, n: i64, direction: Direction) -> Vec<Point> {
+        let mut points: Vec<Point> = vec![];
+
+        for i in range {
+            let point = match direction {
+                Direction::Horizontal => Point(n, i),
+                Direction::Vertical => Point(i, n)
+            };
+            points.push(point)
+        }
+
+        points
+    }
+
+    fn expand_diagonal(range_x: Vec<i64>, range_y: Vec<i64>) -> Vec<Point> {
+        let mut points: Vec<Point> = vec![];
+
+        for (a, b) in zip(range_x, range_y) {
+            points.push(Point(a, b))
+        }
+
+        points
+    }
+
+    fn generate_range(start: i64, end: i64) -> Vec<i64> {
+        match start.cmp(&end) {
+            Ordering::Less => {
+                (start..=end).collect::<Vec<i64>>()
+            },
+            Ordering::Equal => vec![start],
+            Ordering::Greater => {
+                (end..=start).rev().collect::<Vec<i64>>()
+            }
+        }
+    }
+
+    fn point_count(&self, mode: Rasterization) -> i64 {
+        let (dx, dy) = ((self.end.0 - self.start.0).abs(), (self.end.1 - self.start.1).abs());
+
+        match mode {
+            Rasterization::Lattice => gcd(dx, dy) + 1,
+            _ => dx.max(dy) + 1
+        }
+    }
+
+    fn check_length(&self, mode: Rasterization, line: usize) -> Result<(), VentError> {
+        match self.point_count(mode) {
+            points if points > MAX_LINE_POINTS => Err(VentError::TooLong { line, points, limit: MAX_LINE_POINTS }),
+            _ => Ok(())
+        }
+    }
+}
+
+fn check_total(total: i64, points: i64, lines: usize) -> Result<i64, VentError> {
+    match total + points {
+        total if total > MAX_TOTAL_POINTS => Err(VentError::TooManyPoints { lines, points: total, limit: MAX_TOTAL_POINTS }),
+        total => Ok(total)
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    match b {
+        0 => a,
+        _ => gcd(b, a % b)
+    }
+}
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone, Copy)]
+pub struct Voxel(i64, i64, i64);
+
+#[derive(Debug, Clone)]
+pub struct Line3 {
+    start: Voxel,
+    end: Voxel,
+    number: usize
+}
+
+impl Line3 {
+    fn deltas(&self) -> [i64; 3] {
+        [self.end.0 - self.start.0, self.end.1 - self.start.1, self.end.2 - self.start.2]
+    }
+
+    fn is_straight(&self) -> bool {
+        self.deltas().iter().filter(|&&delta| delta != 0).count() <= 1
+    }
+
+    fn is_diagonal(&self) -> bool {
+        let lengths = self.deltas().iter().map(|delta| delta.abs()).filter(|&length| length != 0).collect::<Vec<i64>>();
+
+        lengths.iter().all(|&length| length == lengths[0])
+    }
+
+    fn point_count(&self, line: usize) -> Result<i64, VentError> {
+        if !self.is_diagonal() {
+            return Err(VentError::UnsupportedSlope3d { line, start: self.start, end: self.end });
+        }
+
+        match self.deltas().iter().map(|delta| delta.abs()).max().unwrap_or(0) + 1 {
+            points if points > MAX_LINE_POINTS => Err(VentError::TooLong { line, points, limit: MAX_LINE_POINTS }),
+            points => Ok(points)
+        }
+    }
+
+    pub fn voxels(&self, line: usize) -> Result<Vec<Voxel>, VentError> {
+        let steps = self.point_count(line)? - 1;
+        let [dx, dy, dz] = self.deltas();
+
+        Ok((0..=steps)
+            .map(|i| Voxel(self.start.0 + i * dx.signum(), self.start.1 + i * dy.signum(), self.start.2 + i * dz.signum()))
+            .collect::<Vec<Voxel>>())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Vents {
+    Planar(Vec<Line>),
+    Spatial(Vec<Line3>)
+}
+
+impl Vents {
+    pub fn dimension(&self) -> usize {
+        match self {
+            Vents::Planar(_) => 2,
+            Vents::Spatial(_) => 3
+        }
+    }
+}
+
+pub fn count_voxel_overlaps<'a, I: IntoIterator<Item = &'a Line3>>(lines: I) -> Result<usize, VentError> {
+    let lines = lines.into_iter().collect::<Vec<&Line3>>();
+    lines.iter().try_fold(0, |total, line| check_total(total, line.point_count(line.number)?, line.number))?;
+
+    let mut voxels: HashMap<Voxel, u32> = HashMap::new();
+
+    for line in lines {
+        for voxel in line.voxels(line.number)? {
+            *voxels.entry(voxel).or_insert(0) += 1;
+        }
+    }
+
+    Ok(voxels.values().filter(|&&count| count > 1).count())
+}
+
+#[aoc_generator(day5)]
+pub fn vents_generator(input: &str) -> Result<Vents, VentError> {
+    let dimension = input
+        .lines()
+        .enumerate()
+        .find(|(_, l)| !l.trim().is_empty())
+        .map_or(Ok(2), |(i, l)| parse_endpoints(l, i + 1, None).map(|(start, _)| start.len()))?;
+
+    match dimension {
+        3 => Ok(Vents::Spatial(
+            parse_lines(input, 3)?
+                .into_iter()
+                .map(|(number, (start, end))| Line3 {
+                    start: Voxel(start[0], start[1], start[2]),
+                    end: Voxel(end[0], end[1], end[2]),
+                    number
+                })
+                .collect::<Vec<Line3>>()
+        )),
+        _ => Ok(Vents::Planar(input_generator(input)?))
+    }
+}
+
+pub fn input_generator(input: &str) -> Result<Vec<Line>, VentError> {
+    Ok(parse_lines(input, 2)?
+        .into_iter()
+        .map(|(number, (start, end))| Line {
+            start: Point(start[0], start[1]),
+            end: Point(end[0], end[1]),
+            number
+        })
+        .collect::<Vec<Line>>())
+}
+
+type Endpoints = (Vec<i64>, Vec<i64>);
+
+fn parse_lines(input: &str, dimension: usize) -> Result<Vec<(usize, Endpoints)>, VentError> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| parse_endpoints(l, i + 1, Some(dimension)).map(|endpoints| (i + 1, endpoints)))
+        .collect()
+}
+
+fn parse_endpoints(input: &str, line: usize, dimension: Option<usize>) -> Result<Endpoints, VentError> {
+    let invalid = || VentError::InvalidLine { line, text: input.trim().to_string() };
+    let (start, end) = input.trim().split_once(" -> ").ok_or_else(invalid)?;
+    let (start, end) = (parse_coordinates(start, line)?, parse_coordinates(end, line)?);
+    let dimension = dimension.unwrap_or(start.len());
+
+    match (start.len(), end.len()) {
+        (a, b) if a == dimension && b == dimension && (dimension == 2 || dimension == 3) => Ok((start, end)),
+        _ => Err(invalid())
+    }
+}
+
+fn parse_coordinates(input: &str, line: usize) -> Result<Vec<i64>, VentError> {
+    input
+        .split(',')
+        .map(|val| {
+            let value = val
+                .trim()
+                .parse::<i64>()
+                .map_err(|_| VentError::InvalidCoordinate { line, value: val.trim().to_string() })?;
+
+            match value.abs() <= MAX_COORDINATE {
+                true => Ok(value),
+                false => Err(VentError::OutOfRange { line, limit: MAX_COORDINATE })
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Family {
+    Horizontal,
+    Vertical,
+    Diagonal,
+    AntiDiagonal
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Segment {
+    family: Family,
+    key: i64,
+    lo: i64,
+    hi: i64
+}
+
+impl Segment {
+    fn from_point(family: Family, point: Point) -> (i64, i64) {
+        match family {
+            Family::Horizontal => (point.1, point.0),
+            Family::Vertical => (point.0, point.1),
+            Family::Diagonal => (point.1 - point.0, point.0),
+            Family::AntiDiagonal => (point.1 + point.0, point.0)
+        }
+    }
+
+    fn contains(&self, point: Point) -> bool {
+        let (key, param) = Segment::from_point(self.family, point);
+
+        key == self.key && self.lo <= param && param <= self.hi
+    }
+
+    fn x_range(&self) -> (i64, i64) {
+        match self.family {
+            Family::Vertical => (self.key, self.key),
+            _ => (self.lo, self.hi)
+        }
+    }
+
+    fn crossing(&self, other: &Segment) -> Option<Point> {
+        let point = match (self.family, other.family) {
+            (a, b) if a == b => return None,
+            (Family::Horizontal, Family::Vertical) => Point(other.key, self.key),
+            (Family::Horizontal, Family::Diagonal) => Point(self.key - other.key, self.key),
+            (Family::Horizontal, Family::AntiDiagonal) => Point(other.key - self.key, self.key),
+            (Family::Vertical, Family::Diagonal) => Point(self.key, self.key + other.key),
+            (Family::Vertical, Family::AntiDiagonal) => Point(self.key, other.key - self.key),
+            (Family::Diagonal, Family::AntiDiagonal) => {
+                if (self.key + other.key) % 2 != 0 {
+                    return None;
+                }
+                Point((other.key - self.key) / 2, (self.key + other.key) / 2)
+            },
+            _ => return other.crossing(self)
+        };
+
+        match self.contains(point) && other.contains(point) {
+            true => Some(point),
+            false => None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OverlapAlgorithm {
+    Dense,
+    Pairwise,
+    Hashed
+}
+
+const DENSE_LIMIT: i64 = 1 << 24;
+
+fn bounding_box(lines: &[&Line]) -> Option<(Point, Point)> {
+    let points = lines.iter().flat_map(|line| vec![line.start, line.end]).collect::<Vec<Point>>();
+    let min_x = points.iter().map(|point| point.0).min()?;
+    let max_x = points.iter().map(|point| point.0).max()?;
+    let min_y = points.iter().map(|point| point.1).min()?;
+    let max_y = points.iter().map(|point| point.1).max()?;
+
+    Some((Point(min_x, min_y), Point(max_x, max_y)))
+}
+
+pub fn choose_algorithm<'a, I: IntoIterator<Item = &'a Line>>(lines: I, mode: Rasterization) -> OverlapAlgorithm {
+    let lines = lines.into_iter().collect::<Vec<&Line>>();
+    let area = bounding_box(&lines).map_or(Some(0), |(min, max)| {
+        (max.0 - min.0).checked_add(1)?.checked_mul((max.1 - min.1).checked_add(1)?)
+    });
+
+    match (area, mode) {
+        (Some(area), _) if area <= DENSE_LIMIT => OverlapAlgorithm::Dense,
+        (_, Rasterization::Diagonal) => OverlapAlgorithm::Pairwise,
+        _ => OverlapAlgorithm::Hashed
+    }
+}
+
+enum Counts {
+    Dense { origin: Point, width: usize, cells: Vec<u32> },
+    Sparse(HashMap<Point, u32>)
+}
+
+pub struct OverlapMap {
+    lines: Vec<Line>,
+    mode: Rasterization,
+    counts: Counts
+}
+
+impl OverlapMap {
+    pub fn new<'a, I: IntoIterator<Item = &'a Line>>(lines: I, mode: Rasterization) -> Result<OverlapMap, VentError> {
+        let lines = lines.into_iter().collect::<Vec<&Line>>();
+        let algorithm = choose_algorithm(lines.iter().cloned(), mode);
+
+        OverlapMap::with_algorithm(&lines, mode, algorithm)
+    }
+
+    fn with_algorithm(lines: &[&Line], mode: Rasterization, algorithm: OverlapAlgorithm) -> Result<OverlapMap, VentError> {
+        lines.iter().try_fold(0, |total, line| {
+            line.check_length(mode, line.number)?;
+            check_total(total, line.point_count(mode), line.number)
+        })?;
+
+        let counts = match (algorithm, bounding_box(lines)) {
+            (OverlapAlgorithm::Dense, Some((min, max))) => {
+                let width = (max.0 - min.0 + 1) as usize;
+                let mut cells: Vec<u32> = vec![0; width * (max.1 - min.1 + 1) as usize];
+
+                for line in lines {
+                    line.walk(mode, line.number, |point| {
+                        cells[(point.1 - min.1) as usize * width + (point.0 - min.0) as usize] += 1;
+                    })?;
+                }
+
+                Counts::Dense { origin: min, width, cells }
+            },
+            _ => {
+                let mut points_map: HashMap<Point, u32> = HashMap::new();
+
+                for line in lines {
+                    line.walk(mode, line.number, |point| {
+                        let count = points_map.entry(point).or_insert(0);
+                        *count += 1;
+                    })?;
+                }
+
+                Counts::Sparse(points_map)
+            }
+        };
+
+        Ok(OverlapMap { lines: lines.iter().map(|&line| line.clone()).collect(), mode, counts })
+    }
+
+    pub fn count_at(&self, point: Point) -> u32 {
+        match &self.counts {
+            Counts::Dense { origin, width, cells } => {
+                let (x, y) = (point.0 - origin.0, point.1 - origin.1);
+
+                if x < 0 || y < 0 || x as usize >= *width {
+                    return 0;
+                }
+
+                cells.get(y as usize * width + x as usize).cloned().unwrap_or(0)
+            },
+            Counts::Sparse(points_map) => points_map.get(&point).cloned().unwrap_or(0)
+        }
+    }
+
+    pub fn counts(&self) -> Vec<(Point, u32)> {
+        let mut counts = match &self.counts {
+            Counts::Dense { origin, width, cells } => {
+                cells
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &count)| count > 0)
+                    .map(|(i, &count)| (Point(origin.0 + (i % width) as i64, origin.1 + (i / width) as i64), count))
+                    .collect::<Vec<(Point, u32)>>()
+            },
+            Counts::Sparse(points_map) => points_map.iter().map(|(&point, &count)| (point, count)).collect()
+        };
+        counts.sort_by_key(|(point, _)| (point.1, point.0));
+
+        counts
+    }
+
+    pub fn points_at_least(&self, threshold: u32) -> usize {
+        match &self.counts {
+            Counts::Dense { cells, .. } => cells.iter().filter(|&&count| count > 0 && count >= threshold).count(),
+            Counts::Sparse(points_map) => points_map.values().filter(|&&count| count >= threshold).count()
+        }
+    }
+
+    pub fn most_overlapped(&self) -> (u32, Vec<Point>) {
+        let counts = self.counts();
+        let max = counts.iter().map(|&(_, count)| count).max().unwrap_or(0);
+
+        (max, counts.into_iter().filter(|&(_, count)| count == max && max > 0).map(|(point, _)| point).collect())
+    }
+
+    pub fn covering_lines(&self, point: Point) -> Vec<usize> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.covers(point, self.mode))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        bounding_box(&self.lines.iter().collect::<Vec<&Line>>())
+    }
+
+    pub fn write_ppm<W: Write>(&self, output: &mut W, scale: usize) -> io::Result<()> {
+        let (min, max) = self.bounds().unwrap_or((Point(0, 0), Point(0, 0)));
+        let (width, height) = ((max.0 - min.0 + 1) as usize, (max.1 - min.1 + 1) as usize);
+        let scale = scale.max(1);
+        let pixels = width
+            .checked_mul(scale)
+            .zip(height.checked_mul(scale))
+            .filter(|&(w, h)| w.checked_mul(h).is_some_and(|pixels| pixels <= DENSE_LIMIT as usize));
+
+        let (scaled_width, scaled_height) = match pixels {
+            Some(size) => size,
+            None => return Err(io::Error::new(io::ErrorKind::InvalidInput, "vent field is too large to render"))
+        };
+
+        let (most, _) = self.most_overlapped();
+        write!(output, "P6\n{} {}\n255\n", scaled_width, scaled_height)?;
+
+        for y in min.1..=max.1 {
+            let row = (min.0..=max.0)
+                .flat_map(|x| heat_color(self.count_at(Point(x, y)), most).repeat(scale))
+                .collect::<Vec<u8>>();
+
+            for _ in 0..scale {
+                output.write_all(&row)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+const HEAT_RAMP: [[f64; 3]; 4] = [[40.0, 40.0, 160.0], [200.0, 40.0, 40.0], [255.0, 220.0, 60.0], [255.0, 255.0, 255.0]];
+
+fn heat_color(count: u32, most: u32) -> Vec<u8> {
+    if count == 0 {
+        return vec![0, 0, 0];
+    }
+
+    let position = match most {
+        0 | 1 => 0.0,
+        _ => (count - 1) as f64 / (most - 1) as f64 * (HEAT_RAMP.len() - 1) as f64
+    };
+    let i = (position.floor() as usize).min(HEAT_RAMP.len() - 2);
+    let t = position - i as f64;
+
+    (0..3)
+        .map(|c| (HEAT_RAMP[i][c] + (HEAT_RAMP[i + 1][c] - HEAT_RAMP[i][c]) * t).round() as u8)
+        .collect()
+}
+
+pub fn write_svg<W: Write>(lines: &[Line], output: &mut W, scale: usize) -> io::Result<()> {
+    let (min, max) = bounding_box(&lines.iter().collect::<Vec<&Line>>()).unwrap_or((Point(0, 0), Point(0, 0)));
+    let (width, height) = (max.0 - min.0 + 1, max.1 - min.1 + 1);
+    let scale = scale.max(1) as i64;
+
+    writeln!(
+        output,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">",
+        width * scale,
+        height * scale,
+        min.0 as f64 - 0.5,
+        min.1 as f64 - 0.5,
+        width,
+        height
+    )?;
+    writeln!(output, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"black\"/>", min.0 as f64 - 0.5, min.1 as f64 - 0.5, width, height)?;
+
+    for line in lines {
+        let color = match (line.is_straight(), line.is_diagonal()) {
+            (true, _) => "#4fc3f7",
+            (false, true) => "#ffb74d",
+            (false, false) => "#e57373"
+        };
+
+        writeln!(
+            output,
+            "<line x1=\"{}\" y1=\"{}\" x2=\"{}\" y2=\"{}\" stroke=\"{}\" stroke-width=\"0.3\" stroke-linecap=\"round\" stroke-opacity=\"0.6\"/>",
+            line.start.0, line.start.1, line.end.0, line.end.1, color
+        )?;
+    }
+
+    writeln!(output, "</svg>")
+}
+
+/// Counts diagonal overlaps without rasterizing by testing every pair of
+/// segments whose x-ranges intersect, then walking each supporting line's
+/// coverage. Runs in O(n log n + p) for p such pairs, which degrades to O(n²)
+/// when long segments span most of the input.
+fn count_pairwise(lines: &[&Line]) -> Result<usize, VentError> {
+    let mut segments = lines
+        .iter()
+        .map(|line| line.segment(line.number))
+        .collect::<Result<Vec<Segment>, VentError>>()?;
+    segments.sort_by_key(|segment| segment.x_range());
+
+    let mut crossings: Vec<Point> = vec![];
+
+    for (i, segment) in segments.iter().enumerate() {
+        let (_, max_x) = segment.x_range();
+
+        for other in segments[i + 1..].iter().take_while(|other| other.x_range().0 <= max_x) {
+            if let Some(point) = segment.crossing(other) {
+                crossings.push(point);
+            }
+        }
+    }
+
+    crossings.sort_by_key(|point| (point.0, point.1));
+    crossings.dedup();
+
+    let mut total = crossings.len();
+    segments.sort_by_key(|segment| (segment.family, segment.key, segment.lo));
+
+    for family_segments in segments.chunk_by(|a, b| a.family == b.family && a.key == b.key) {
+        let family = family_segments[0].family;
+        let key = family_segments[0].key;
+        let mut projected = crossings
+            .iter()
+            .map(|&point| Segment::from_point(family, point))
+            .filter(|&(k, _)| k == key)
+            .map(|(_, param)| param)
+            .collect::<Vec<i64>>();
+        projected.sort_unstable();
+
+        let mut events = family_segments
+            .iter()
+            .flat_map(|segment| vec![(segment.lo, 1), (segment.hi + 1, -1)])
+            .collect::<Vec<(i64, i32)>>();
+        events.sort_unstable();
+
+        let mut coverage = 0;
+        let mut previous = 0;
+
+        for (position, delta) in events {
+            if coverage > 1 && position > previous {
+                let crossed = projected.partition_point(|&p| p < position) - projected.partition_point(|&p| p < previous);
+                total += (position - previous) as usize - crossed;
+            }
+
+            coverage += delta;
+            previous = position;
+        }
+    }
+
+    Ok(total)
+}
+
+// Crossing denominators grow to 8L² and the cross-multiplied comparisons in
+// `contains` and `y_at` to roughly 2^9·L⁶ for coordinates bounded by L, so
+// 2^19 is the largest power of two that keeps them inside i128.
+const EXACT_LIMIT: i64 = 1 << 19;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rational {
+    num: i128,
+    den: i128
+}
+
+impl Rational {
+    fn new(num: i128, den: i128) -> Rational {
+        let g = gcd128(num.abs(), den.abs()).max(1);
+        let sign = if den < 0 { -1 } else { 1 };
+
+        Rational { num: sign * num / g, den: sign * den / g }
+    }
+
+    fn integer(value: i64) -> Rational {
+        Rational { num: value as i128, den: 1 }
+    }
+
+    pub fn to_integer(&self) -> Option<i64> {
+        match self.den {
+            1 => Some(self.num as i64),
+            _ => None
+        }
+    }
+}
+
+impl Ord for Rational {
+    fn cmp(&self, other: &Rational) -> Ordering {
+        (self.num * other.den).cmp(&(other.num * self.den))
+    }
+}
+
+impl PartialOrd for Rational {
+    fn partial_cmp(&self, other: &Rational) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for Rational {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.den {
+            1 => write!(f, "{}", self.num),
+            _ => write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
 
-#[derive(Debug)]
-pub struct Line {
-    start: Point,
-    end: Point
+fn gcd128(a: i128, b: i128) -> i128 {
+    match b {
+        0 => a,
+        _ => gcd128(b, a % b)
+    }
 }
 
-impl Line {
-    fn is_straight(&self) -> bool {
-       self.start.0 == self.end.0 || self.start.1 == self.end.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RationalPoint(pub Rational, pub Rational);
+
+impl RationalPoint {
+    fn from_point(point: Point) -> RationalPoint {
+        RationalPoint(Rational::integer(point.0), Rational::integer(point.1))
     }
 
-    fn intersecting_points(&self) -> Vec<Point> {
-        let range_x: Vec<usize> = Line::generate_range(self.start.0, self.end.0);
-        let range_y: Vec<usize> = Line::generate_range(self.start.1, self.end.1);
+    pub fn to_point(&self) -> Option<Point> {
+        Some(Point(self.0.to_integer()?, self.1.to_integer()?))
+    }
+}
 
-        match (range_x.len(), range_y.len()) {
-            (1, _) => Line::expand(range_y, self.start.0, Direction::Horizontal),
-            (_, 1) => Line::expand(range_x, self.start.1, Direction::Vertical),
-            (_, _) => Line::expand_diagonal(range_x, range_y)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Intersection {
+    Point { at: RationalPoint, lines: Vec<usize> },
+    Overlap { start: Point, end: Point, lines: Vec<usize> }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ExactSegment {
+    left: Point,
+    right: Point
+}
+
+impl ExactSegment {
+    fn new(line: &Line, number: usize) -> Result<ExactSegment, VentError> {
+        let coordinates = [line.start.0, line.start.1, line.end.0, line.end.1];
+
+        if coordinates.iter().any(|value| value.abs() > EXACT_LIMIT) {
+            return Err(VentError::OutOfRange { line: number, limit: EXACT_LIMIT });
         }
+
+        let (left, right) = match (line.start.0, line.start.1) <= (line.end.0, line.end.1) {
+            true => (line.start, line.end),
+            false => (line.end, line.start)
+        };
+
+        Ok(ExactSegment { left, right })
     }
 
-    fn expand(range: Vec<usize>, n: usize, direction: Direction) -> Vec<Point> {
-        let mut points: Vec<Point> = vec![];
+    fn is_point(&self) -> bool {
+        self.left == self.right
+    }
 
-        for i in range {
-            let point = match direction {
-                Direction::Horizontal => Point(n, i),
-                Direction::Vertical => Point(i, n)
-            };
-            points.push(point)
+    fn is_vertical(&self) -> bool {
+        self.left.0 == self.right.0
+    }
+
+    fn delta(&self) -> (i128, i128) {
+        ((self.right.0 - self.left.0) as i128, (self.right.1 - self.left.1) as i128)
+    }
+
+    fn sweep_key(&self) -> (bool, Rational) {
+        let (dx, dy) = self.delta();
+
+        match self.is_vertical() {
+            true => (true, Rational::integer(0)),
+            false => (false, Rational::new(dy, dx))
         }
+    }
 
-        points
+    fn y_at(&self, point: RationalPoint) -> Rational {
+        if self.is_vertical() {
+            return point.1;
+        }
+
+        let (dx, dy) = self.delta();
+        let x = point.0;
+        let (x0, y0) = (self.left.0 as i128, self.left.1 as i128);
+
+        Rational::new(y0 * dx * x.den + (x.num - x0 * x.den) * dy, dx * x.den)
     }
 
-    fn expand_diagonal(range_x: Vec<usize>, range_y: Vec<usize>) -> Vec<Point> {
-        let mut points: Vec<Point> = vec![];
+    fn contains(&self, point: RationalPoint) -> bool {
+        let (dx, dy) = self.delta();
+        let (x, y) = (point.0, point.1);
+        let (x0, y0) = (self.left.0 as i128, self.left.1 as i128);
+        let (min_y, max_y) = (self.left.1.min(self.right.1), self.left.1.max(self.right.1));
 
-        for (a, b) in zip(range_x, range_y) {
-            points.push(Point(a, b))
+        dx * (y.num * x.den - y0 * x.den * y.den) == dy * (x.num * y.den - x0 * x.den * y.den)
+            && Rational::integer(self.left.0) <= x
+            && x <= Rational::integer(self.right.0)
+            && Rational::integer(min_y) <= y
+            && y <= Rational::integer(max_y)
+    }
+
+    fn crossing(&self, other: &ExactSegment) -> Option<RationalPoint> {
+        let (rx, ry) = self.delta();
+        let (sx, sy) = other.delta();
+        let (qx, qy) = ((other.left.0 - self.left.0) as i128, (other.left.1 - self.left.1) as i128);
+        let denominator = rx * sy - ry * sx;
+
+        if denominator == 0 {
+            return None;
         }
 
-        points
+        let sign = denominator.signum();
+        let (d, t, u) = (denominator * sign, (qx * sy - qy * sx) * sign, (qx * ry - qy * rx) * sign);
+
+        if t < 0 || t > d || u < 0 || u > d {
+            return None;
+        }
+
+        Some(RationalPoint(
+            Rational::new(self.left.0 as i128 * d + t * rx, d),
+            Rational::new(self.left.1 as i128 * d + t * ry, d)
+        ))
     }
 
-    fn generate_range(start: usize, end: usize) -> Vec<usize> {
-        match end > start {
-            true => {
-                (start..=end).map(usize::from).collect::<Vec<usize>>()
-            },
-            false => {
-                (end..=start).rev().map(usize::from).collect::<Vec<usize>>()
+    fn supporting_line(&self) -> (i64, i64, i64) {
+        let (dx, dy) = (self.right.0 - self.left.0, self.right.1 - self.left.1);
+        let g = gcd(dx.abs(), dy.abs());
+        let (a, b) = match (dy / g, -dx / g) {
+            (a, b) if a < 0 || (a == 0 && b < 0) => (-a, -b),
+            (a, b) => (a, b)
+        };
+
+        (a, b, a * self.left.0 + b * self.left.1)
+    }
+}
+
+/// Finds every point where two or more segments meet by sweeping left to right
+/// over endpoint and crossing events. The status is a plain `Vec` rescanned at
+/// each event, so this runs in O((n + k)·n) for k crossings rather than the
+/// O((n + k) log n) of a Bentley–Ottmann sweep with a balanced status tree.
+fn sweep_crossings(segments: &[ExactSegment]) -> BTreeMap<RationalPoint, BTreeSet<usize>> {
+    let mut queue: BTreeMap<RationalPoint, Vec<usize>> = BTreeMap::new();
+
+    for (id, segment) in segments.iter().enumerate() {
+        queue.entry(RationalPoint::from_point(segment.left)).or_default().push(id);
+        queue.entry(RationalPoint::from_point(segment.right)).or_default();
+    }
+
+    let mut crossings: BTreeMap<RationalPoint, BTreeSet<usize>> = BTreeMap::new();
+    let mut status: Vec<usize> = vec![];
+
+    while let Some((point, starting)) = queue.pop_first() {
+        let (containing, rest): (Vec<usize>, Vec<usize>) = status.iter().partition(|&&id| segments[id].contains(point));
+        let involved = starting.iter().chain(containing.iter()).cloned().collect::<BTreeSet<usize>>();
+
+        if involved.len() > 1 {
+            crossings.entry(point).or_default().extend(involved);
+        }
+
+        let mut inserted = starting
+            .iter()
+            .chain(containing.iter())
+            .filter(|&&id| !segments[id].is_point() && RationalPoint::from_point(segments[id].right) != point)
+            .cloned()
+            .collect::<Vec<usize>>();
+        inserted.sort_by(|&a, &b| segments[a].sweep_key().cmp(&segments[b].sweep_key()).then(a.cmp(&b)));
+
+        status = rest;
+        let position = status.iter().filter(|&&id| segments[id].y_at(point) < point.1).count();
+        status.splice(position..position, inserted.iter().cloned());
+
+        let mut neighbours = vec![];
+        match inserted.len() {
+            0 => neighbours.push(position),
+            n => neighbours.extend([position, position + n])
+        }
+
+        for index in neighbours.into_iter().filter(|&index| index > 0 && index < status.len()) {
+            if let Some(crossing) = segments[status[index - 1]].crossing(&segments[status[index]]) {
+                if crossing > point {
+                    queue.entry(crossing).or_default();
+                }
             }
         }
     }
+
+    crossings
 }
 
-#[aoc_generator(day5)]
-pub fn input_generator(input: &str) -> Vec<Line> {
-    input
-        .lines()
-        .map(|l| {
-            let parts = l.trim().split(" -> ").collect::<Vec<&str>>();
+fn collinear_overlaps(segments: &[ExactSegment]) -> Vec<Intersection> {
+    let mut groups: BTreeMap<(i64, i64, i64), Vec<usize>> = BTreeMap::new();
 
-            Line {
-                start: parse_point(parts[0]),
-                end: parse_point(parts[1])
+    for (id, segment) in segments.iter().enumerate().filter(|(_, segment)| !segment.is_point()) {
+        groups.entry(segment.supporting_line()).or_default().push(id);
+    }
+
+    let mut overlaps: Vec<Intersection> = vec![];
+
+    for ids in groups.values().filter(|ids| ids.len() > 1) {
+        let mut events = ids
+            .iter()
+            .flat_map(|&id| vec![(segments[id].left, id, true), (segments[id].right, id, false)])
+            .collect::<Vec<(Point, usize, bool)>>();
+        events.sort_by_key(|&(point, id, _)| (point.0, point.1, id));
+
+        let mut active: BTreeSet<usize> = BTreeSet::new();
+        let mut previous: Option<Point> = None;
+
+        for at_point in events.chunk_by(|a, b| a.0 == b.0) {
+            let point = at_point[0].0;
+
+            if let Some(start) = previous {
+                if active.len() > 1 {
+                    overlaps.push(Intersection::Overlap { start, end: point, lines: active.iter().cloned().collect() });
+                }
             }
-        })
-        .collect::<Vec<Line>>()
-}
 
-fn parse_point(input: &str) -> Point {
-    let parts = input
-        .split(',')
-        .map(|val| val.parse::<usize>().unwrap())
-        .collect::<Vec<usize>>();
+            for &(_, id, starting) in at_point {
+                match starting {
+                    true => active.insert(id),
+                    false => active.remove(&id)
+                };
+            }
 
-    Point(parts[0], parts[1])
-}
+            previous = Some(point);
+        }
+    }
 
-#[aoc(day5, part1)]
-pub fn part1(input: &Vec<Line>)  -> usize {
-    let mut points_map: HashMap<Point, i32> = HashMap::new();
+    overlaps
+}
 
-    input
+pub fn exact_intersections(lines: &[Line]) -> Result<Vec<Intersection>, VentError> {
+    let segments = lines
         .iter()
-        .filter(|line| line.is_straight())
-        .for_each(|line| {
-            line
-                .intersecting_points()
-                .iter()
-                .for_each(|point| {
-                    let count = points_map.entry(point.clone()).or_insert(0);
-                    *count += 1;
-                });
-        });
+        .map(|line| ExactSegment::new(line, line.number))
+        .collect::<Result<Vec<ExactSegment>, VentError>>()?;
 
-    points_map
+    let mut intersections = sweep_crossings(&segments)
         .into_iter()
-        .map(|(_key, val)| val)
-        .filter(|val| {
-            val > &1
-        })
-        .count()
+        .map(|(at, ids)| Intersection::Point { at, lines: ids.into_iter().collect() })
+        .collect::<Vec<Intersection>>();
+    intersections.extend(collinear_overlaps(&segments));
+
+    Ok(intersections)
 }
 
-#[aoc(day5, part2)]
-pub fn part2(input: &Vec<Line>)  -> usize {
-    let mut points_map: HashMap<Point, i32> = HashMap::new();
+pub fn exact_overlap_count(lines: &[Line]) -> Result<usize, VentError> {
+    let intersections = exact_intersections(lines)?;
+    let mut merged: Vec<(Point, Point)> = vec![];
 
-    input
-        .iter()
-        .for_each(|line| {
-            line
-                .intersecting_points()
+    for intersection in &intersections {
+        if let Intersection::Overlap { start, end, .. } = intersection {
+            let line = ExactSegment { left: *start, right: *end }.supporting_line();
+
+            match merged.last_mut() {
+                Some(last) if last.1 == *start && ExactSegment { left: last.0, right: last.1 }.supporting_line() == line => {
+                    last.1 = *end
+                },
+                _ => merged.push((*start, *end))
+            }
+        }
+    }
+
+    let lattice = |start: Point, end: Point| gcd((end.0 - start.0).abs(), (end.1 - start.1).abs()) as usize + 1;
+    let mut total = merged.iter().map(|&(start, end)| lattice(start, end)).sum::<usize>();
+
+    for intersection in &intersections {
+        if let Intersection::Point { at, .. } = intersection {
+            if at.to_point().is_none() {
+                continue;
+            }
+
+            let covering = merged
                 .iter()
-                .for_each(|point| {
-                    let count = points_map.entry(point.clone()).or_insert(0);
-                    *count += 1;
-                });
-        });
+                .filter(|&&(start, end)| ExactSegment { left: start, right: end }.contains(*at))
+                .count();
 
-    points_map
-        .into_iter()
-        .map(|(_key, val)| val)
-        .filter(|val| {
-            val > &1
-        })
-        .count()
+            match covering {
+                0 => total += 1,
+                n => total -= n - 1
+            }
+        }
+    }
+
+    Ok(total)
+}
+
+pub fn count_overlaps<'a, I: IntoIterator<Item = &'a Line>>(lines: I, mode: Rasterization) -> Result<usize, VentError> {
+    count_overlaps_at(lines, mode, 2)
+}
+
+pub fn count_overlaps_at<'a, I: IntoIterator<Item = &'a Line>>(lines: I, mode: Rasterization, threshold: u32) -> Result<usize, VentError> {
+    let lines = lines.into_iter().collect::<Vec<&Line>>();
+    let algorithm = choose_algorithm(lines.iter().cloned(), mode);
+
+    count_overlaps_with(&lines, mode, algorithm, threshold)
+}
+
+fn count_overlaps_with(lines: &[&Line], mode: Rasterization, algorithm: OverlapAlgorithm, threshold: u32) -> Result<usize, VentError> {
+    match algorithm {
+        OverlapAlgorithm::Pairwise if mode == Rasterization::Diagonal && threshold == 2 => count_pairwise(lines),
+        OverlapAlgorithm::Pairwise => Ok(OverlapMap::with_algorithm(lines, mode, OverlapAlgorithm::Hashed)?.points_at_least(threshold)),
+        _ => Ok(OverlapMap::with_algorithm(lines, mode, algorithm)?.points_at_least(threshold))
+    }
+}
+
+#[aoc(day5, part1)]
+pub fn part1(input: &Vents) -> Result<usize, VentError> {
+    match input {
+        Vents::Planar(lines) => count_overlaps(lines.iter().filter(|line| line.is_straight()), Rasterization::Diagonal),
+        Vents::Spatial(lines) => count_voxel_overlaps(lines.iter().filter(|line| line.is_straight()))
+    }
+}
+
+#[aoc(day5, part2)]
+pub fn part2(input: &Vents) -> Result<usize, VentError> {
+    match input {
+        Vents::Planar(lines) => count_overlaps(lines.iter(), Rasterization::Diagonal),
+        Vents::Spatial(lines) => count_voxel_overlaps(lines.iter())
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
 
     #[test]
     fn test_part1() {
@@ -162,7 +1171,7 @@ mod tests {
             0,0 -> 8,8
             5,5 -> 8,2";
 
-        assert_eq!(part1(&input_generator(input)), 5)
+        assert_eq!(part1(&vents_generator(input).unwrap()), Ok(5))
     }
 
     #[test]
@@ -179,24 +1188,26 @@ mod tests {
             0,0 -> 8,8
             5,5 -> 8,2";
 
-        assert_eq!(part2(&input_generator(input)), 12)
+        assert_eq!(part2(&vents_generator(input).unwrap()), Ok(12))
     }
 
     #[test]
     fn test_line_is_straight() {
         let line = Line {
             start: Point(1, 1),
-            end: Point(1, 3)
+            end: Point(1, 3),
+            number: 1
         };
 
-        assert_eq!(line.is_straight(), true)
+        assert!(line.is_straight())
     }
 
     #[test]
     fn test_intersecting_points_x() {
         let line = Line {
             start: Point(1, 1),
-            end: Point(3, 1)
+            end: Point(3, 1),
+            number: 1
         };
 
         assert_eq!(line.intersecting_points(), vec![Point(1, 1), Point(2, 1), Point(3, 1)])
@@ -206,7 +1217,8 @@ mod tests {
     fn test_intersecting_points_y() {
         let line = Line {
             start: Point(1, 1),
-            end: Point(1, 3)
+            end: Point(1, 3),
+            number: 1
         };
 
         assert_eq!(line.intersecting_points(), vec![Point(1, 1), Point(1, 2), Point(1, 3)])
@@ -216,7 +1228,8 @@ mod tests {
     fn test_intersecting_points_misc() {
         let line = Line {
             start: Point(0, 9),
-            end: Point(2, 9)
+            end: Point(2, 9),
+            number: 1
         };
 
         assert_eq!(line.intersecting_points(), vec![Point(0, 9), Point(1, 9), Point(2, 9)])
@@ -226,7 +1239,8 @@ mod tests {
     fn test_intersecting_diagonal() {
         let line = Line {
             start: Point(1, 1),
-            end: Point(3, 3)
+            end: Point(3, 3),
+            number: 1
         };
 
 
@@ -237,7 +1251,8 @@ mod tests {
     fn test_intersecting_diagonal_2() {
         let line = Line {
             start: Point(9, 7),
-            end: Point(7, 9)
+            end: Point(7, 9),
+            number: 1
         };
 
         assert_eq!(line.intersecting_points(), vec![Point(9, 7), Point(8, 8), Point(7, 9)])
@@ -245,8 +1260,8 @@ mod tests {
 
     #[test]
     fn point_index() {
-        let point_a = Point(0, 0);
-        let point_b = Point(5, 0);
+        let _point_a = Point(0, 0);
+        let _point_b = Point(5, 0);
     }
 
     #[test]
@@ -256,4 +1271,390 @@ mod tests {
 
         assert_eq!(Line::generate_range(start, end), vec![1, 2, 3, 4, 5])
     }
+
+    #[test]
+    fn negative_coordinates() {
+        let line = Line {
+            start: Point(-2, 1),
+            end: Point(0, -1),
+            number: 1
+        };
+
+        assert_eq!(line.intersecting_points(), vec![Point(-2, 1), Point(-1, 0), Point(0, -1)])
+    }
+
+    #[test]
+    fn lattice_points() {
+        let line = Line {
+            start: Point(0, 0),
+            end: Point(6, -4),
+            number: 1
+        };
+
+        assert_eq!(line.rasterize(Rasterization::Lattice, 1), Ok(vec![Point(0, 0), Point(3, -2), Point(6, -4)]));
+        assert_eq!(
+            line.rasterize(Rasterization::Diagonal, 7),
+            Err(VentError::UnsupportedSlope { line: 7, start: Point(0, 0), end: Point(6, -4) })
+        )
+    }
+
+    #[test]
+    fn bresenham_points() {
+        let line = Line {
+            start: Point(0, 0),
+            end: Point(4, 2),
+            number: 1
+        };
+
+        assert_eq!(
+            line.rasterize(Rasterization::Bresenham, 1),
+            Ok(vec![Point(0, 0), Point(1, 1), Point(2, 1), Point(3, 2), Point(4, 2)])
+        )
+    }
+
+    #[test]
+    fn unsupported_slope_in_part2() {
+        let input = "0,0 -> 2,2\n0,0 -> 3,1";
+
+        assert_eq!(
+            part2(&vents_generator(input).unwrap()),
+            Err(VentError::UnsupportedSlope { line: 2, start: Point(0, 0), end: Point(3, 1) })
+        );
+        assert_eq!(count_overlaps(&input_generator(input).unwrap(), Rasterization::Lattice), Ok(1))
+    }
+
+    #[test]
+    fn overlap_algorithms_agree() {
+        let input = "\
+            0,9 -> 5,9
+            8,0 -> 0,8
+            9,4 -> 3,4
+            2,2 -> 2,1
+            7,0 -> 7,4
+            6,4 -> 2,0
+            0,9 -> 2,9
+            3,4 -> 1,4
+            0,0 -> 8,8
+            5,5 -> 8,2
+            4,4 -> 4,4
+            1,0 -> 1,0
+            0,1 -> 2,3
+            3,0 -> 3,9";
+        let lines = input_generator(input).unwrap();
+        let refs = lines.iter().collect::<Vec<&Line>>();
+
+        for algorithm in [OverlapAlgorithm::Dense, OverlapAlgorithm::Pairwise, OverlapAlgorithm::Hashed] {
+            assert_eq!(count_overlaps_with(&refs[..10], Rasterization::Diagonal, algorithm, 2), Ok(12));
+            assert_eq!(count_overlaps_with(&refs, Rasterization::Diagonal, algorithm, 2), Ok(16));
+        }
+    }
+
+    #[test]
+    fn overlap_algorithms_agree_on_random_lines() {
+        let mut rng = StdRng::seed_from_u64(41);
+
+        for _ in 0..50 {
+            let lines = (1..=40)
+                .map(|number| {
+                    let start = Point(rng.gen_range(-15..15), rng.gen_range(-15..15));
+                    let length = rng.gen_range(-10..10);
+                    let end = match rng.gen_range(0..4) {
+                        0 => Point(start.0 + length, start.1),
+                        1 => Point(start.0, start.1 + length),
+                        2 => Point(start.0 + length, start.1 + length),
+                        _ => Point(start.0 + length, start.1 - length)
+                    };
+
+                    Line { start, end, number }
+                })
+                .collect::<Vec<Line>>();
+            let refs = lines.iter().collect::<Vec<&Line>>();
+            let expected = count_overlaps_with(&refs, Rasterization::Diagonal, OverlapAlgorithm::Hashed, 2);
+
+            assert_eq!(count_overlaps_with(&refs, Rasterization::Diagonal, OverlapAlgorithm::Dense, 2), expected);
+            assert_eq!(count_overlaps_with(&refs, Rasterization::Diagonal, OverlapAlgorithm::Pairwise, 2), expected);
+        }
+    }
+
+    #[test]
+    fn huge_coordinates_use_pairwise() {
+        let input = "0,0 -> 1000000000,0\n500000000,-5 -> 500000000,5\n0,0 -> 10,0\n-3,-3 -> 3,3";
+        let lines = input_generator(input).unwrap();
+
+        assert_eq!(choose_algorithm(&lines, Rasterization::Diagonal), OverlapAlgorithm::Pairwise);
+        assert_eq!(choose_algorithm(&lines, Rasterization::Lattice), OverlapAlgorithm::Hashed);
+        assert_eq!(choose_algorithm(&lines[2..], Rasterization::Diagonal), OverlapAlgorithm::Dense);
+        assert_eq!(count_overlaps(&lines, Rasterization::Diagonal), Ok(12))
+    }
+
+    #[test]
+    fn overlap_map_queries() {
+        let input = "\
+            0,9 -> 5,9
+            8,0 -> 0,8
+            9,4 -> 3,4
+            2,2 -> 2,1
+            7,0 -> 7,4
+            6,4 -> 2,0
+            0,9 -> 2,9
+            3,4 -> 1,4
+            0,0 -> 8,8
+            5,5 -> 8,2";
+        let lines = input_generator(input).unwrap();
+        let refs = lines.iter().collect::<Vec<&Line>>();
+
+        for algorithm in [OverlapAlgorithm::Dense, OverlapAlgorithm::Hashed] {
+            let map = OverlapMap::with_algorithm(&refs, Rasterization::Diagonal, algorithm).unwrap();
+
+            assert_eq!(map.count_at(Point(4, 4)), 3);
+            assert_eq!(map.count_at(Point(9, 9)), 0);
+            assert_eq!(map.count_at(Point(-1, 4)), 0);
+            assert_eq!(map.points_at_least(1), 39);
+            assert_eq!(map.points_at_least(2), 12);
+            assert_eq!(map.points_at_least(3), 2);
+            assert_eq!(map.most_overlapped(), (3, vec![Point(4, 4), Point(6, 4)]));
+            assert_eq!(map.counts().len(), 39);
+        }
+
+        let map = OverlapMap::new(&lines, Rasterization::Diagonal).unwrap();
+        assert_eq!(map.covering_lines(Point(4, 4)), vec![1, 2, 8]);
+        assert_eq!(map.covering_lines(Point(0, 0)), vec![8]);
+        assert_eq!(map.covering_lines(Point(1, 1)), vec![8]);
+        assert!(map.covering_lines(Point(9, 0)).is_empty());
+        assert_eq!(count_overlaps_at(&lines, Rasterization::Diagonal, 3), Ok(2))
+    }
+
+    #[test]
+    fn render_ppm() {
+        let lines = input_generator("0,0 -> 2,0\n1,0 -> 1,1").unwrap();
+        let map = OverlapMap::new(&lines, Rasterization::Diagonal).unwrap();
+        let mut output: Vec<u8> = vec![];
+
+        map.write_ppm(&mut output, 2).unwrap();
+
+        let header = b"P6\n6 4\n255\n";
+        assert_eq!(&output[..header.len()], header);
+        assert_eq!(output.len(), header.len() + 6 * 4 * 3);
+
+        let pixels = &output[header.len()..];
+        assert_eq!(&pixels[0..6], &[40, 40, 160, 40, 40, 160]);
+        assert_eq!(&pixels[6..9], &[255, 255, 255]);
+        assert_eq!(&pixels[18 * 2..18 * 2 + 3], &[0, 0, 0]);
+
+        for scale in [4097, usize::MAX / 2, usize::MAX] {
+            let mut output: Vec<u8> = vec![];
+
+            assert_eq!(map.write_ppm(&mut output, scale).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+            assert!(output.is_empty());
+        }
+    }
+
+    #[test]
+    fn render_svg() {
+        let lines = input_generator("0,0 -> 2,0\n1,0 -> 3,2\n0,1 -> 3,2").unwrap();
+        let mut output: Vec<u8> = vec![];
+
+        write_svg(&lines, &mut output, 10).unwrap();
+
+        let svg = String::from_utf8(output).unwrap();
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"40\" height=\"30\" viewBox=\"-0.5 -0.5 4 3\">"));
+        assert_eq!(svg.matches("<line ").count(), 3);
+        assert!(svg.contains("x1=\"1\" y1=\"0\" x2=\"3\" y2=\"2\" stroke=\"#ffb74d\""));
+        assert!(svg.contains("stroke=\"#e57373\""));
+        assert!(svg.ends_with("</svg>\n"))
+    }
+
+    #[test]
+    fn exact_intersection_points() {
+        let lines = input_generator("0,0 -> 1,1\n0,1 -> 1,0\n0,0 -> 4,0\n2,0 -> 6,0\n3,-1 -> 3,1").unwrap();
+        let half = Rational::new(1, 2);
+
+        assert_eq!(half.to_string(), "1/2");
+        assert_eq!(exact_intersections(&lines), Ok(vec![
+            Intersection::Point { at: RationalPoint::from_point(Point(0, 0)), lines: vec![0, 2] },
+            Intersection::Point { at: RationalPoint(half, half), lines: vec![0, 1] },
+            Intersection::Point { at: RationalPoint::from_point(Point(1, 0)), lines: vec![1, 2] },
+            Intersection::Point { at: RationalPoint::from_point(Point(2, 0)), lines: vec![2, 3] },
+            Intersection::Point { at: RationalPoint::from_point(Point(3, 0)), lines: vec![2, 3, 4] },
+            Intersection::Point { at: RationalPoint::from_point(Point(4, 0)), lines: vec![2, 3] },
+            Intersection::Overlap { start: Point(2, 0), end: Point(4, 0), lines: vec![2, 3] }
+        ]));
+        assert_eq!(exact_overlap_count(&lines), Ok(5));
+        assert_eq!(
+            exact_overlap_count(&input_generator("0,0 -> 99999999,0").unwrap()),
+            Err(VentError::OutOfRange { line: 1, limit: EXACT_LIMIT })
+        )
+    }
+
+    #[test]
+    fn exact_arithmetic_near_limit() {
+        let limit = EXACT_LIMIT;
+        let lines = [
+            Line { start: Point(-limit, -limit + 1), end: Point(limit, limit - 3), number: 1 },
+            Line { start: Point(-limit, limit - 2), end: Point(limit - 1, -limit), number: 2 },
+            Line { start: Point(-limit + 5, -limit), end: Point(limit - 7, limit), number: 3 }
+        ];
+
+        assert!(exact_intersections(&lines).is_ok());
+        assert_eq!(exact_overlap_count(&lines), Ok(0));
+        assert_eq!(
+            exact_overlap_count(&[Line { start: Point(-limit - 1, 0), end: Point(limit, 1), number: 1 }]),
+            Err(VentError::OutOfRange { line: 1, limit })
+        )
+    }
+
+    #[test]
+    fn exact_count_matches_rasterization() {
+        let input = "\
+            0,9 -> 5,9
+            8,0 -> 0,8
+            9,4 -> 3,4
+            2,2 -> 2,1
+            7,0 -> 7,4
+            6,4 -> 2,0
+            0,9 -> 2,9
+            3,4 -> 1,4
+            0,0 -> 8,8
+            5,5 -> 8,2";
+
+        assert_eq!(exact_overlap_count(&input_generator(input).unwrap()), Ok(12));
+
+        let corner = input_generator("0,0 -> 2,0\n0,0 -> 2,0\n2,0 -> 2,3\n2,0 -> 2,3").unwrap();
+        assert_eq!(exact_overlap_count(&corner), Ok(6));
+        assert_eq!(exact_overlap_count(&corner), count_overlaps(&corner, Rasterization::Lattice));
+
+        let mut rng = StdRng::seed_from_u64(44);
+
+        for round in 0..100 {
+            let lines = (1..=30)
+                .map(|number| {
+                    let start = Point(rng.gen_range(-10..10), rng.gen_range(-10..10));
+                    let end = match round % 2 {
+                        0 => {
+                            let length = rng.gen_range(-8..8);
+                            match rng.gen_range(0..4) {
+                                0 => Point(start.0 + length, start.1),
+                                1 => Point(start.0, start.1 + length),
+                                2 => Point(start.0 + length, start.1 + length),
+                                _ => Point(start.0 + length, start.1 - length)
+                            }
+                        },
+                        _ => Point(start.0 + rng.gen_range(-6..6), start.1 + rng.gen_range(-6..6))
+                    };
+
+                    Line { start, end, number }
+                })
+                .collect::<Vec<Line>>();
+
+            assert_eq!(exact_overlap_count(&lines), count_overlaps(&lines, Rasterization::Lattice));
+        }
+    }
+
+    #[test]
+    fn spatial_vents() {
+        let input = "\
+            0,0,0 -> 4,0,0
+            2,0,0 -> 2,0,3
+            0,0,0 -> 3,3,3
+            3,0,0 -> 0,3,0
+            1,1,1 -> 1,1,1
+            4,0,0 -> 0,4,1";
+        let vents = vents_generator(input).unwrap();
+
+        assert_eq!(vents.dimension(), 3);
+        assert_eq!(vents_generator("0,9 -> 5,9").unwrap().dimension(), 2);
+        assert_eq!(part1(&vents_generator(&input.lines().take(2).collect::<Vec<&str>>().join("\n")).unwrap()), Ok(1));
+        assert_eq!(
+            part2(&vents),
+            Err(VentError::UnsupportedSlope3d { line: 6, start: Voxel(4, 0, 0), end: Voxel(0, 4, 1) })
+        );
+
+        let supported = match &vents {
+            Vents::Spatial(lines) => lines[..5].to_vec(),
+            Vents::Planar(_) => unreachable!()
+        };
+        assert_eq!(supported[3].voxels(4), Ok(vec![Voxel(3, 0, 0), Voxel(2, 1, 0), Voxel(1, 2, 0), Voxel(0, 3, 0)]));
+        assert_eq!(count_voxel_overlaps(&supported), Ok(4));
+        assert_eq!(part1(&Vents::Spatial(supported.clone())), Ok(1));
+        assert_eq!(part2(&Vents::Spatial(supported)), Ok(4))
+    }
+
+    #[test]
+    fn degenerate_lines() {
+        let point = Line { start: Point(3, 3), end: Point(3, 3), number: 1 };
+
+        assert_eq!(Line::generate_range(4, 4), vec![4]);
+        assert_eq!(Line::generate_range(5, 1), vec![5, 4, 3, 2, 1]);
+        for mode in [Rasterization::Diagonal, Rasterization::Lattice, Rasterization::Bresenham] {
+            assert_eq!(point.rasterize(mode, 1), Ok(vec![Point(3, 3)]));
+        }
+
+        let forward = Line { start: Point(0, 0), end: Point(4, 2), number: 1 };
+        let reversed = Line { start: Point(4, 2), end: Point(0, 0), number: 1 };
+        let mut points = reversed.rasterize(Rasterization::Lattice, 1).unwrap();
+        points.reverse();
+        assert_eq!(forward.rasterize(Rasterization::Lattice, 1).unwrap(), points);
+
+        let lines = input_generator("3,3 -> 3,3\n3,3 -> 3,3\n0,3 -> 6,3\n6,3 -> 0,3\n5,5 -> 1,1\n\n").unwrap();
+        let refs = lines.iter().collect::<Vec<&Line>>();
+        assert_eq!(lines.len(), 5);
+
+        for algorithm in [OverlapAlgorithm::Dense, OverlapAlgorithm::Pairwise, OverlapAlgorithm::Hashed] {
+            assert_eq!(count_overlaps_with(&refs, Rasterization::Diagonal, algorithm, 2), Ok(7));
+        }
+        assert_eq!(exact_overlap_count(&lines), Ok(7));
+        assert_eq!(count_overlaps_at(&lines, Rasterization::Diagonal, 5), Ok(1))
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert_eq!(input_generator("0,0 -> 1,1\n0,0 => 1,1").unwrap_err(), VentError::InvalidLine { line: 2, text: "0,0 => 1,1".to_string() });
+        assert_eq!(input_generator("0,0 -> 1").unwrap_err(), VentError::InvalidLine { line: 1, text: "0,0 -> 1".to_string() });
+        assert_eq!(input_generator("0,x -> 1,1").unwrap_err(), VentError::InvalidCoordinate { line: 1, value: "x".to_string() });
+        assert_eq!(
+            vents_generator("0,0,0 -> 1,1,1\n0,0 -> 1,1").unwrap_err(),
+            VentError::InvalidLine { line: 2, text: "0,0 -> 1,1".to_string() }
+        );
+        assert_eq!(vents_generator("1,2,3,4 -> 1,2,3,4").unwrap_err(), VentError::InvalidLine { line: 1, text: "1,2,3,4 -> 1,2,3,4".to_string() });
+        assert_eq!(
+            input_generator("0,0 -> 9223372036854775807,0").unwrap_err(),
+            VentError::OutOfRange { line: 1, limit: MAX_COORDINATE }
+        );
+
+        assert_eq!(
+            part2(&vents_generator("0,0 -> 3,3\n\n0,0 -> 3,1").unwrap()),
+            Err(VentError::UnsupportedSlope { line: 3, start: Point(0, 0), end: Point(3, 1) })
+        );
+
+        let lines = input_generator("0,0 -> 3,3\n\n0,0 -> 1000000000,0").unwrap();
+        assert_eq!(
+            count_overlaps(lines.iter().filter(|line| line.is_straight()), Rasterization::Bresenham),
+            Err(VentError::TooLong { line: 3, points: 1000000001, limit: MAX_LINE_POINTS })
+        );
+
+        let lines = input_generator("0,0 -> 1000000000,1").unwrap();
+        assert_eq!(
+            count_overlaps(&lines, Rasterization::Bresenham),
+            Err(VentError::TooLong { line: 1, points: 1000000001, limit: MAX_LINE_POINTS })
+        );
+        assert_eq!(count_overlaps(&lines, Rasterization::Lattice), Ok(0));
+        assert_eq!(
+            count_voxel_overlaps(&[Line3 { start: Voxel(0, 0, 0), end: Voxel(0, 0, 1 << 30), number: 1 }]),
+            Err(VentError::TooLong { line: 1, points: (1 << 30) + 1, limit: MAX_LINE_POINTS })
+        );
+
+        let lines = input_generator(&["0,0 -> 67108863,0"; 5].join("\n")).unwrap();
+        assert_eq!(
+            count_overlaps(&lines, Rasterization::Lattice),
+            Err(VentError::TooManyPoints { lines: 5, points: 5 << 26, limit: MAX_TOTAL_POINTS })
+        );
+        assert_eq!(
+            count_voxel_overlaps(&(1..=5).map(|number| Line3 { start: Voxel(0, 0, 0), end: Voxel(0, 0, (1 << 26) - 1), number }).collect::<Vec<Line3>>()),
+            Err(VentError::TooManyPoints { lines: 5, points: 5 << 26, limit: MAX_TOTAL_POINTS })
+        );
+        assert_eq!(
+            VentError::TooManyPoints { lines: 5, points: 5 << 26, limit: MAX_TOTAL_POINTS }.to_string(),
+            "lines 1 to 5 cover 335544320 points in total, more than the limit of 268435456"
+        )
+    }
 }
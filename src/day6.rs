@@ -1,29 +1,470 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+const RESET_TIMER_VAR: &str = "DAY6_RESET_TIMER";
+const NEWBORN_TIMER_VAR: &str = "DAY6_NEWBORN_TIMER";
+const MORTALITY_VAR: &str = "DAY6_MORTALITY";
+
+#[derive(Debug, PartialEq)]
+pub enum PopulationError {
+    Overflow { days: usize },
+    InvalidTimer { value: String },
+    InvalidModel { reason: String },
+    ZeroModulus,
+    TooManyDays { days: usize, states: usize }
+}
+
+impl fmt::Display for PopulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PopulationError::Overflow { days } => write!(f, "population after {} days does not fit in a u128", days),
+            PopulationError::InvalidTimer { value } => write!(f, "{:?} is not a valid lanternfish timer", value),
+            PopulationError::InvalidModel { reason } => write!(f, "invalid lanternfish model: {}", reason),
+            PopulationError::ZeroModulus => write!(f, "population cannot be counted modulo 0"),
+            PopulationError::TooManyDays { days, states } => {
+                write!(f, "{} days is too many to simulate one day at a time across {} states", days, states)
+            }
+        }
+    }
+}
+
+impl Error for PopulationError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Method {
+    Rotate,
+    Matrix
+}
+
+const MATRIX_THRESHOLD: usize = 512;
+const MATRIX_STATES: usize = 128;
+const MAX_STATES: usize = 1 << 20;
+const ROTATE_BUDGET: usize = 1 << 24;
+
+type Timers = Vec<u128>;
+type Matrix = Vec<Vec<u128>>;
+type Successors = Vec<Vec<usize>>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyPopulation {
+    pub day: usize,
+    pub total: u128,
+    pub buckets: Vec<u128>
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LanternfishModel {
+    pub reset_timer: usize,
+    pub newborn_timer: usize,
+    pub mortality: Option<usize>
+}
+
+impl Default for LanternfishModel {
+    fn default() -> LanternfishModel {
+        LanternfishModel { reset_timer: 6, newborn_timer: 8, mortality: None }
+    }
+}
+
+impl LanternfishModel {
+    pub fn new(reset_timer: usize, newborn_timer: usize, mortality: Option<usize>) -> Result<LanternfishModel, PopulationError> {
+        let model = LanternfishModel { reset_timer, newborn_timer, mortality };
+
+        if mortality == Some(0) {
+            return Err(PopulationError::InvalidModel { reason: "mortality age must be at least 1".to_string() });
+        }
+
+        match model.timers().checked_mul(mortality.unwrap_or(1)) {
+            Some(states) if states <= MAX_STATES => Ok(model),
+            _ => Err(PopulationError::InvalidModel { reason: format!("more than {} timer and age states", MAX_STATES) })
+        }
+    }
+
+    pub fn from_env() -> Result<LanternfishModel, PopulationError> {
+        let default = LanternfishModel::default();
+        let read = |name: &str, fallback: Option<usize>| match env::var(name) {
+            Ok(value) => value
+                .trim()
+                .parse::<usize>()
+                .map(Some)
+                .map_err(|_| PopulationError::InvalidModel { reason: format!("{} must be a number, not {:?}", name, value) }),
+            Err(_) => Ok(fallback)
+        };
+
+        LanternfishModel::new(
+            read(RESET_TIMER_VAR, Some(default.reset_timer))?.unwrap(),
+            read(NEWBORN_TIMER_VAR, Some(default.newborn_timer))?.unwrap(),
+            read(MORTALITY_VAR, default.mortality)?
+        )
+    }
+
+    fn timers(&self) -> usize {
+        self.reset_timer.max(self.newborn_timer) + 1
+    }
+
+    fn states(&self) -> usize {
+        self.timers() * self.mortality.unwrap_or(1)
+    }
+
+    fn index(&self, age: usize, timer: usize) -> usize {
+        match self.mortality {
+            Some(_) => age * self.timers() + timer,
+            None => timer
+        }
+    }
+
+    fn successors(&self, state: usize) -> Vec<usize> {
+        let (age, timer) = (state / self.timers(), state % self.timers());
+        let next_age = match self.mortality {
+            Some(limit) if age + 1 >= limit => None,
+            _ => Some(age + 1)
+        };
+        let mut successors = vec![];
+
+        if let Some(next_age) = next_age {
+            successors.push(match timer {
+                0 => self.index(next_age, self.reset_timer),
+                _ => self.index(next_age, timer - 1)
+            });
+        }
+
+        if timer == 0 {
+            successors.push(self.index(0, self.newborn_timer));
+        }
+
+        successors
+    }
+
+    pub fn choose_method(&self, days: usize) -> Method {
+        match days <= MATRIX_THRESHOLD || self.states() > MATRIX_STATES {
+            true => Method::Rotate,
+            false => Method::Matrix
+        }
+    }
+
+    fn rotate_days(&self) -> usize {
+        ROTATE_BUDGET / self.states()
+    }
+
+    fn successor_table(&self) -> Successors {
+        (0..self.states()).map(|state| self.successors(state)).collect()
+    }
+
+    fn checked_method(&self, days: usize) -> Result<Method, PopulationError> {
+        match self.choose_method(days) {
+            Method::Rotate if days > self.rotate_days() => Err(PopulationError::TooManyDays { days, states: self.states() }),
+            method => Ok(method)
+        }
+    }
+
+    pub fn parse_timers(&self, input: &str) -> Result<Timers, PopulationError> {
+        let mut lifetimes = vec![0; self.states()];
+
+        for val in input.trim().split(',').filter(|val| !val.trim().is_empty()) {
+            let invalid = || PopulationError::InvalidTimer { value: val.trim().to_string() };
+            let n = val.trim().parse::<usize>().map_err(|_| invalid())?;
+
+            if n >= self.timers() {
+                return Err(invalid());
+            }
+
+            lifetimes[self.index(0, n)] += 1;
+        }
+
+        Ok(lifetimes)
+    }
+
+    pub fn simulate(&self, input: &str, days: usize) -> Result<u128, PopulationError> {
+        match self.checked_method(days)? {
+            Method::Rotate => self.run_via_rotate(input, days),
+            Method::Matrix => match self.run_via_matrix(input, days) {
+                Err(PopulationError::Overflow { .. }) if days <= self.rotate_days() => self.run_via_rotate(input, days),
+                result => result
+            }
+        }
+    }
+
+    pub fn count_modulo(&self, input: &str, days: usize, modulus: u64) -> Result<u64, PopulationError> {
+        if modulus == 0 {
+            return Err(PopulationError::ZeroModulus);
+        }
+
+        let modulus = Some(modulus as u128);
+        let timers = self.parse_timers(input)?;
+        let timers = match self.checked_method(days)? {
+            Method::Rotate => self.rotate(timers, days, modulus),
+            Method::Matrix => self.power(days, modulus).and_then(|matrix| apply(&matrix, &timers, modulus))
+        };
+
+        timers
+            .and_then(|timers| total(&timers, modulus))
+            .map(|count| count as u64)
+            .ok_or(PopulationError::Overflow { days })
+    }
+
+    pub fn run_via_rotate(&self, input: &str, days: usize) -> Result<u128, PopulationError> {
+        self.rotate(self.parse_timers(input)?, days, None)
+            .and_then(|timers| total(&timers, None))
+            .ok_or(PopulationError::Overflow { days })
+    }
+
+    pub fn run_via_matrix(&self, input: &str, days: usize) -> Result<u128, PopulationError> {
+        let timers = self.parse_timers(input)?;
+
+        if timers.iter().all(|&count| count == 0) {
+            return Ok(0);
+        }
+
+        self.power(days, None)
+            .and_then(|matrix| apply(&matrix, &timers, None))
+            .and_then(|timers| total(&timers, None))
+            .ok_or(PopulationError::Overflow { days })
+    }
+
+    pub fn series(&self, input: &str, days: usize) -> Result<Vec<DailyPopulation>, PopulationError> {
+        let mut lifetimes = self.parse_timers(input)?;
+        let successors = self.successor_table();
+        let mut series = vec![];
+
+        for day in 0..=days {
+            if day > 0 {
+                lifetimes = step(&lifetimes, &successors, None).ok_or(PopulationError::Overflow { days: day })?;
+            }
+
+            series.push(self.record(day, &lifetimes).ok_or(PopulationError::Overflow { days: day })?);
+        }
+
+        Ok(series)
+    }
+
+    pub fn first_day_exceeding(&self, input: &str, threshold: u128) -> Result<Option<usize>, PopulationError> {
+        let timers = self.parse_timers(input)?;
+
+        if self.mortality.is_some() {
+            return self.scan_exceeding(timers, threshold);
+        }
+
+        let exceeds = |days: usize| match self.simulate(input, days) {
+            Ok(population) => Ok(population > threshold),
+            Err(PopulationError::Overflow { .. }) => Ok(true),
+            Err(e) => Err(e)
+        };
+
+        if exceeds(0)? {
+            return Ok(Some(0));
+        }
+
+        if timers.iter().all(|&count| count == 0) {
+            return Ok(None);
+        }
+
+        let mut high = 1;
+
+        while !exceeds(high)? {
+            high *= 2;
+        }
+
+        let mut low = high / 2;
+
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+
+            match exceeds(mid)? {
+                true => high = mid,
+                false => low = mid
+            }
+        }
+
+        Ok(Some(high))
+    }
+
+    fn scan_exceeding(&self, mut lifetimes: Timers, threshold: u128) -> Result<Option<usize>, PopulationError> {
+        let successors = self.successor_table();
+
+        for day in 0..=self.rotate_days() {
+            if day > 0 {
+                lifetimes = match step(&lifetimes, &successors, None) {
+                    Some(next) => next,
+                    None => return Ok(Some(day))
+                };
+            }
+
+            match total(&lifetimes, None) {
+                Some(0) if day > 0 => return Ok(None),
+                Some(population) if population <= threshold => continue,
+                _ => return Ok(Some(day))
+            }
+        }
+
+        Err(PopulationError::TooManyDays { days: self.rotate_days() + 1, states: self.states() })
+    }
+
+    fn record(&self, day: usize, lifetimes: &Timers) -> Option<DailyPopulation> {
+        let mut buckets: Vec<u128> = vec![0; self.timers()];
+
+        for (state, &count) in lifetimes.iter().enumerate() {
+            let timer = state % self.timers();
+            buckets[timer] = buckets[timer].checked_add(count)?;
+        }
+
+        Some(DailyPopulation { day, total: total(&buckets, None)?, buckets })
+    }
+
+    fn rotate(&self, mut lifetimes: Timers, days: usize, modulus: Option<u128>) -> Option<Timers> {
+        if let Some(m) = modulus {
+            lifetimes.iter_mut().for_each(|count| *count %= m);
+        }
+
+        let successors = self.successor_table();
+
+        for _i in 0..days {
+            lifetimes = step(&lifetimes, &successors, modulus)?;
+        }
+
+        total(&lifetimes, modulus)?;
+
+        Some(lifetimes)
+    }
+
+    fn transition(&self) -> Matrix {
+        let mut matrix = vec![vec![0; self.states()]; self.states()];
+
+        (0..self.states()).for_each(|state| {
+            for successor in self.successors(state) {
+                matrix[successor][state] += 1;
+            }
+        });
+
+        matrix
+    }
+
+    fn power(&self, mut days: usize, modulus: Option<u128>) -> Option<Matrix> {
+        let mut result = identity(self.states());
+        let mut base = self.transition();
+
+        while days > 0 {
+            if days & 1 == 1 {
+                result = multiply(&result, &base, modulus)?;
+            }
+
+            days >>= 1;
+
+            if days > 0 {
+                base = multiply(&base, &base, modulus)?;
+            }
+        }
+
+        Some(result)
+    }
+}
+
 #[aoc(day6, part1)]
-pub fn part1(input: &str) -> usize {
-    run_via_rotate(input, 80)
+pub fn part1(input: &str) -> Result<u128, PopulationError> {
+    LanternfishModel::from_env()?.simulate(input, 80)
 }
 
 #[aoc(day6, part2)]
-pub fn part2(input: &str) -> usize {
-    run_via_rotate(input, 256)
+pub fn part2(input: &str) -> Result<u128, PopulationError> {
+    LanternfishModel::from_env()?.simulate(input, 256)
 }
 
-pub fn run_via_rotate(input: &str, days: usize) -> usize {
-    let mut lifetimes = vec![0, 0, 0, 0, 0, 0, 0, 0, 0];
+pub fn choose_method(days: usize) -> Method {
+    LanternfishModel::default().choose_method(days)
+}
 
-    input
-        .split(",")
-        .for_each(|val| {
-            let n: usize = val.parse::<usize>().unwrap();
-            lifetimes[n] += 1;
-        });
+pub fn simulate(input: &str, days: usize) -> Result<u128, PopulationError> {
+    LanternfishModel::default().simulate(input, days)
+}
+
+pub fn count_modulo(input: &str, days: usize, modulus: u64) -> Result<u64, PopulationError> {
+    LanternfishModel::default().count_modulo(input, days, modulus)
+}
+
+pub fn run_via_rotate(input: &str, days: usize) -> Result<u128, PopulationError> {
+    LanternfishModel::default().run_via_rotate(input, days)
+}
+
+pub fn run_via_matrix(input: &str, days: usize) -> Result<u128, PopulationError> {
+    LanternfishModel::default().run_via_matrix(input, days)
+}
+
+pub fn write_csv<W: Write>(series: &[DailyPopulation], output: &mut W) -> io::Result<()> {
+    let timers = series.first().map_or(0, |day| day.buckets.len());
+    let header = (0..timers).map(|timer| format!("timer{}", timer)).collect::<Vec<String>>();
+
+    writeln!(output, "day,total,{}", header.join(","))?;
+
+    for day in series {
+        let buckets = day.buckets.iter().map(|count| count.to_string()).collect::<Vec<String>>();
+
+        writeln!(output, "{},{},{}", day.day, day.total, buckets.join(","))?;
+    }
+
+    Ok(())
+}
+
+fn add(a: u128, b: u128, modulus: Option<u128>) -> Option<u128> {
+    match modulus {
+        Some(m) => Some((a + b) % m),
+        None => a.checked_add(b)
+    }
+}
 
-    for _i in 0..days {
-        lifetimes.rotate_left(1);
-        lifetimes[6] += lifetimes.last().unwrap().clone();
+fn mul(a: u128, b: u128, modulus: Option<u128>) -> Option<u128> {
+    match modulus {
+        Some(m) => Some(a * b % m),
+        None => a.checked_mul(b)
     }
+}
+
+fn step(lifetimes: &Timers, successors: &Successors, modulus: Option<u128>) -> Option<Timers> {
+    let mut next = vec![0; lifetimes.len()];
+
+    for (state, &count) in lifetimes.iter().enumerate().filter(|(_, &count)| count > 0) {
+        for &successor in &successors[state] {
+            next[successor] = add(next[successor], count, modulus)?;
+        }
+    }
+
+    Some(next)
+}
 
-    lifetimes.iter().sum()
+fn total(timers: &Timers, modulus: Option<u128>) -> Option<u128> {
+    timers.iter().try_fold(0, |total, &count| add(total, count, modulus))
+}
+
+fn identity(size: usize) -> Matrix {
+    let mut matrix = vec![vec![0; size]; size];
+
+    for (i, row) in matrix.iter_mut().enumerate() {
+        row[i] = 1;
+    }
+
+    matrix
+}
+
+fn multiply(a: &Matrix, b: &Matrix, modulus: Option<u128>) -> Option<Matrix> {
+    let size = a.len();
+    let mut product = vec![vec![0; size]; size];
+
+    for (i, row) in product.iter_mut().enumerate() {
+        for (j, cell) in row.iter_mut().enumerate() {
+            *cell = (0..size).try_fold(0, |total, k| add(total, mul(a[i][k], b[k][j], modulus)?, modulus))?;
+        }
+    }
+
+    Some(product)
+}
+
+fn apply(matrix: &Matrix, timers: &Timers, modulus: Option<u128>) -> Option<Timers> {
+    let mut next = vec![0; timers.len()];
+
+    for (i, count) in next.iter_mut().enumerate() {
+        *count = (0..timers.len()).try_fold(0, |total, j| add(total, mul(matrix[i][j], timers[j], modulus)?, modulus))?;
+    }
+
+    Some(next)
 }
 
 #[cfg(test)]
@@ -34,20 +475,186 @@ mod test {
     fn test_part1() {
         let input = "3,4,3,1,2";
 
-        assert_eq!(part1(input), 5934)
+        assert_eq!(LanternfishModel::default().simulate(input, 80), Ok(5934))
     }
 
     #[test]
     fn test_part2() {
         let input = "3,4,3,1,2";
 
-        assert_eq!(part2(input), 26984457539)
+        assert_eq!(LanternfishModel::default().simulate(input, 256), Ok(26984457539))
     }
 
     #[test]
     fn test_rotate() {
         let input = "3,4,3,1,2";
 
-        assert_eq!(run_via_rotate(input, 80), 5934)
+        assert_eq!(run_via_rotate(input, 80), Ok(5934))
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_matrix() {
+        let input = "3,4,3,1,2";
+
+        for days in 0..=300 {
+            assert_eq!(run_via_matrix(input, days), run_via_rotate(input, days));
+        }
+        assert_eq!(choose_method(256), Method::Rotate);
+        assert_eq!(choose_method(1_000_000_000_000), Method::Matrix)
+    }
+
+    #[test]
+    fn test_modulo() {
+        let input = "3,4,3,1,2";
+        let modulus = 1_000_000_007;
+        let wide = Some(modulus as u128);
+        let model = LanternfishModel::default();
+        let timers = model.parse_timers(input).unwrap();
+
+        assert_eq!(count_modulo(input, 256, modulus), Ok(26984457539 % modulus));
+        assert_eq!(
+            count_modulo(input, 2000, modulus),
+            Ok(total(&model.rotate(timers.clone(), 2000, wide).unwrap(), wide).unwrap() as u64)
+        );
+
+        let days = 1_000_000_000_000;
+        let warmed = model.rotate(timers, 500, wide).unwrap();
+        let expected = apply(&model.power(days - 500, wide).unwrap(), &warmed, wide).and_then(|timers| total(&timers, wide));
+
+        assert_eq!(count_modulo(input, days, modulus), Ok(expected.unwrap() as u64));
+        assert_eq!(count_modulo(input, 256, 1), Ok(0));
+        assert_eq!(count_modulo(input, 256, 0), Err(PopulationError::ZeroModulus));
+        assert_eq!(count_modulo(input, days, 0), Err(PopulationError::ZeroModulus))
+    }
+
+    #[test]
+    fn test_overflow() {
+        let input = "3,4,3,1,2";
+        let last = (0..2000).take_while(|&days| run_via_rotate(input, days).is_ok()).last().unwrap();
+
+        assert!(last > 440);
+        assert!(run_via_rotate(input, last).unwrap() > u64::MAX as u128);
+        assert_eq!(simulate(input, last), run_via_rotate(input, last));
+        assert_eq!(run_via_matrix(input, last), run_via_rotate(input, last));
+        assert_eq!(simulate(input, last + 1), Err(PopulationError::Overflow { days: last + 1 }));
+        assert_eq!(simulate(input, 1_000_000_000_000), Err(PopulationError::Overflow { days: 1_000_000_000_000 }));
+        assert_eq!(simulate("", 1_000_000_000_000), Ok(0))
+    }
+
+    #[test]
+    fn test_models() {
+        let input = "3,4,3,1,2";
+        let model = LanternfishModel::new(6, 8, None).unwrap();
+
+        assert_eq!(model, LanternfishModel::default());
+        assert_eq!(model.simulate(input, 18), Ok(26));
+
+        let quick = LanternfishModel::new(2, 3, None).unwrap();
+        assert_eq!(quick.simulate("1", 1), Ok(1));
+        assert_eq!(quick.simulate("1", 2), Ok(2));
+        assert_eq!(quick.simulate("1", 5), Ok(3));
+        assert_eq!(quick.simulate("1", 6), Ok(4));
+        assert_eq!(quick.simulate(input, 1), Err(PopulationError::InvalidTimer { value: "4".to_string() }));
+
+        let mortal = LanternfishModel::new(6, 8, Some(10)).unwrap();
+        assert_eq!(mortal.simulate("0", 9), Ok(3));
+        assert_eq!(mortal.simulate("0", 10), Ok(3));
+
+        let mortal = LanternfishModel::new(6, 8, Some(5)).unwrap();
+        assert_eq!(mortal.simulate("0", 1), Ok(2));
+        assert_eq!(mortal.simulate("0", 5), Ok(1));
+        assert_eq!(mortal.simulate("0", 6), Ok(0));
+        assert_eq!(mortal.simulate("0", 40), Ok(0));
+
+        let mortal = LanternfishModel::new(2, 2, Some(7)).unwrap();
+        assert_eq!(mortal.choose_method(1000), Method::Matrix);
+        for days in [0, 1, 5, 50, 513, 600] {
+            assert_eq!(mortal.run_via_matrix("0,1,2", days), mortal.run_via_rotate("0,1,2", days));
+        }
+
+        let mortal = LanternfishModel::new(6, 8, Some(10)).unwrap();
+        let wide = Some(1_000_000_007);
+        let rotated = mortal.rotate(mortal.parse_timers("3,4,3,1,2").unwrap(), 600, wide).and_then(|timers| total(&timers, wide));
+        assert_eq!(mortal.choose_method(1_000_000_000_000), Method::Matrix);
+        assert_eq!(mortal.count_modulo("3,4,3,1,2", 600, 1_000_000_007), Ok(rotated.unwrap() as u64));
+        assert!(mortal.count_modulo("3,4,3,1,2", 1_000_000_000_000, 1_000_000_007).is_ok());
+
+        let crowded = LanternfishModel::new(6, 8, Some(20)).unwrap();
+        assert_eq!(crowded.choose_method(1_000_000_000_000), Method::Rotate);
+        assert_eq!(crowded.simulate("0", 1000), crowded.run_via_rotate("0", 1000));
+        assert_eq!(
+            crowded.count_modulo("0", 1_000_000_000_000, 1_000_000_007),
+            Err(PopulationError::TooManyDays { days: 1_000_000_000_000, states: 180 })
+        );
+
+        let huge = LanternfishModel::new(8, 8, Some(100_000)).unwrap();
+        assert_eq!(huge.simulate("0", 60_000), Err(PopulationError::TooManyDays { days: 60_000, states: 900_000 }));
+        assert_eq!(huge.simulate("0", 18), huge.run_via_rotate("0", 18));
+
+        assert_eq!(LanternfishModel::new(6, 8, Some(0)), Err(PopulationError::InvalidModel { reason: "mortality age must be at least 1".to_string() }));
+        assert!(LanternfishModel::new(1 << 20, 8, Some(2)).is_err());
+        assert_eq!(simulate("3,x", 1), Err(PopulationError::InvalidTimer { value: "x".to_string() }))
+    }
+
+    #[test]
+    fn test_series() {
+        let input = "3,4,3,1,2";
+        let model = LanternfishModel::default();
+        let series = model.series(input, 80).unwrap();
+
+        assert_eq!(series.len(), 81);
+        assert_eq!(series[0], DailyPopulation { day: 0, total: 5, buckets: vec![0, 1, 1, 2, 1, 0, 0, 0, 0] });
+        assert_eq!(series[1].buckets, vec![1, 1, 2, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(series[18].total, 26);
+        assert_eq!(series[80].total, 5934);
+        assert!(series.iter().all(|day| day.buckets.iter().sum::<u128>() == day.total));
+
+        let mortal = LanternfishModel::new(6, 8, Some(5)).unwrap();
+        assert_eq!(mortal.series("0", 6).unwrap().iter().map(|day| day.total).collect::<Vec<u128>>(), vec![1, 2, 2, 2, 2, 1, 0]);
+        assert_eq!(model.series(input, 2000), Err(PopulationError::Overflow { days: last_day(input) + 1 }))
+    }
+
+    #[test]
+    fn test_first_day_exceeding() {
+        let input = "3,4,3,1,2";
+        let model = LanternfishModel::default();
+        let series = model.series(input, 300).unwrap();
+
+        for threshold in [0, 4, 5, 6, 25, 26, 1000, 5933, 5934, 26984457538, 26984457539] {
+            let expected = series.iter().find(|day| day.total > threshold).map(|day| day.day);
+
+            assert_eq!(model.first_day_exceeding(input, threshold), Ok(expected));
+        }
+        assert_eq!(model.first_day_exceeding(input, 5933), Ok(Some(80)));
+        assert_eq!(model.first_day_exceeding(input, u128::MAX), Ok(Some(last_day(input) + 1)));
+        assert_eq!(model.first_day_exceeding("", 0), Ok(None));
+
+        let mortal = LanternfishModel::new(6, 8, Some(5)).unwrap();
+        assert_eq!(mortal.first_day_exceeding("0", 1), Ok(Some(1)));
+        assert_eq!(mortal.first_day_exceeding("0", 2), Ok(None));
+
+        let stable = LanternfishModel::new(6, 8, Some(10)).unwrap();
+        assert_eq!(stable.first_day_exceeding("0", 1), Ok(Some(1)));
+        assert_eq!(
+            stable.first_day_exceeding("0", 1000),
+            Err(PopulationError::TooManyDays { days: stable.rotate_days() + 1, states: 90 })
+        )
+    }
+
+    #[test]
+    fn test_csv() {
+        let series = LanternfishModel::default().series("3,4,3,1,2", 1).unwrap();
+        let mut output: Vec<u8> = vec![];
+
+        write_csv(&series, &mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "day,total,timer0,timer1,timer2,timer3,timer4,timer5,timer6,timer7,timer8\n0,5,0,1,1,2,1,0,0,0,0\n1,5,1,1,2,1,0,0,0,0,0\n"
+        )
+    }
+
+    fn last_day(input: &str) -> usize {
+        (0..2000).take_while(|&days| run_via_rotate(input, days).is_ok()).last().unwrap()
+    }
+}